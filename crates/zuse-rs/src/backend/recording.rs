@@ -0,0 +1,31 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::Backend;
+use crate::cad::DrawList;
+use anyhow::Result;
+
+/// Keeps the last `DrawList` instead of rendering it, for inspecting what `Cad` draws without
+/// a GPU. Clones share the recording, so keep one and hand another to `Cad::with_backend`.
+#[derive(Debug, Clone, Default)]
+pub struct RecordingBackend {
+    last: Rc<RefCell<Option<DrawList>>>,
+}
+
+impl RecordingBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The list passed to the last `draw`, if any.
+    pub fn last(&self) -> Option<DrawList> {
+        self.last.borrow().clone()
+    }
+}
+
+impl Backend for RecordingBackend {
+    fn draw(&mut self, draw_list: &DrawList) -> Result<()> {
+        *self.last.borrow_mut() = Some(draw_list.clone());
+        Ok(())
+    }
+}
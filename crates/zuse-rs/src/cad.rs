@@ -1,19 +1,50 @@
 mod draw_list;
 mod io;
+mod probe;
+mod sim_monitor;
+mod svg;
+mod theme;
 
 use crate::symbol;
 
+use super::backend::Backend;
+#[cfg(feature = "wasm")]
 use super::backend::GlowBackend;
 use super::font::FONT;
-use super::schematic;
-pub use draw_list::{Color, DrawCmd, DrawList};
+use super::schematic::{self, Rectangular};
+pub use draw_list::{Color, DrawList};
+#[cfg(feature = "wasm")]
+pub use draw_list::Index;
 pub use io::Io;
+use probe::Probe;
+use sim_monitor::SimMonitor;
+pub use theme::Theme;
 use nalgebra::Vector2;
+use rstar::primitives::Line;
+use rstar::RTreeObject;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use wasm_bindgen::prelude::*;
 
+/// Screen pixels within which a moved component snaps to another one's row or column.
+const SNAP_TOLERANCE: f32 = 8.;
+
+/// Screen pixels panned by one arrow key press.
+const KEY_PAN_STEP: f32 = 50.;
+
+/// Zoom limits, in screen pixels per world unit.
+const MIN_SCALE: f32 = 0.1;
+const MAX_SCALE: f32 = 16.0;
+
+/// Screen pixels left around a region zoomed to.
+const FIT_MARGIN: f32 = 20.;
+
+/// How far, in grid steps, an imported component may be moved to avoid an overlap.
+const IMPORT_SEARCH_STEPS: i32 = 10;
+
 #[wasm_bindgen]
 pub struct Cad {
-    backend: GlowBackend,
+    backend: Box<dyn Backend>,
     transform: Transform,
     grid_size: u32,
     draw_list: DrawList,
@@ -22,13 +53,121 @@ pub struct Cad {
     tool_state: ToolState,
     sch_state: schematic::State,
     circuit: Option<zuse_core::Circuit>,
+    sim_monitor: Option<SimMonitor>,
+    rubber_band: bool,
+    text_height: f32,
+    label_halo: bool,
+    zoom_sensitivity: f32,
+    pan_sensitivity: f32,
+    invert_wheel: bool,
+    invert_zoom: bool,
+    selection: Vec<u32>,
+    snap_guides: bool,
+    /// Point to net map with the schematic revision it was computed for.
+    net_cache: Option<(u64, HashMap<[i32; 2], u32>)>,
+    highlighted_net: Option<u32>,
+    render_style: RenderStyle,
+    probes: Vec<Probe>,
+    last_import_report: ImportReport,
+    modifiers: io::Modifiers,
+    held_keys: io::HeldKeys,
+    /// Mouse button that places and selects.
+    primary_button: u8,
+    /// Mouse button that cancels.
+    secondary_button: u8,
+    read_only: bool,
+    /// Simulation passes run since the simulation started.
+    sim_tick: u64,
+    auto_connect: bool,
+    theme: Theme,
+    stats_cache: Option<(u64, schematic::NetlistStats)>,
+    has_area: bool,
+    units: Units,
+    /// Physical length of one grid step in `units`.
+    units_per_grid: f32,
+    /// Asks the user for a string; `prompt` unless replaced in tests.
+    prompt: fn(&str, &str) -> Option<String>,
+}
+
+/// Cosmetic options that don't affect the schematic itself.
+#[derive(Debug, Clone, Default)]
+struct RenderStyle {
+    /// Fill wire corners and tees with a disc so bends look continuous.
+    round_wire_corners: bool,
+    /// Wire and symbol stroke width in screen pixels, overriding their world-unit thickness so
+    /// lines stay e.g. a hairline at any zoom.
+    screen_stroke: Option<f32>,
+    crossing_style: CrossingStyle,
+}
+
+/// How wires crossing without connecting are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum CrossingStyle {
+    /// Plainly; only connections get a dot.
+    #[default]
+    Dot,
+    /// The horizontal wire hops over the vertical one.
+    Hop,
+}
+
+/// Units coordinates and lengths are shown in. Display only; the schematic itself is always in
+/// world units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Units {
+    Grid,
+    Millimeter,
+    Inch,
+}
+
+/// Outcome of the last `import_components`.
+#[derive(Debug, Default, Serialize)]
+struct ImportReport {
+    placed: usize,
+    /// Placed, but not where requested.
+    moved: Vec<ImportedComponent>,
+    rejected: Vec<ImportedComponent>,
+}
+
+#[derive(Debug, Serialize)]
+struct ImportedComponent {
+    label: String,
+    /// Requested position.
+    position: (i32, i32),
+    /// Actual position of a moved component.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    placed_at: Option<(i32, i32)>,
+    /// Why a component was rejected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
 }
 
+/// The active tool.
+///
+/// Wiring goes through these states:
+///
+/// - `Selection` --"w"--> `Wiring`, starting at the cursor
+/// - `ReadyToWire` --"w" or click--> `Wiring`, starting at the cursor
+/// - `Wiring` --"w" or click--> `Wiring`, adding a segment towards the cursor
+/// - `Wiring` --double-click--> `ReadyToWire`, committing the segments
+/// - any --Escape--> `Selection`, discarding uncommitted segments
+/// - `Wiring` --right click--> `Selection`, discarding uncommitted segments
 enum ToolState {
     Selection,
     ReadyToWire,
     Wiring(Wiring),
     PlacingComponent(symbol::Kind, schematic::RotMirror),
+    DraggingWire(Line<[i32; 2]>, Vector2<i32>),
+    MovingComponent(MovingComponent),
+}
+
+struct MovingComponent {
+    component: schematic::Component,
+    /// From the pointer to the component's origin at the time it was picked up.
+    offset: Vector2<f32>,
+    /// Where the component would be dropped.
+    position: Vector2<i32>,
+    /// Coordinates of the other components it is aligned with, if any.
+    guides: (Option<i32>, Option<i32>),
 }
 
 enum Wire {
@@ -49,53 +188,106 @@ impl Wiring {
         }
     }
 
+    /// Adds a segment from `last` towards `cursor`, alternating between horizontal and
+    /// vertical. Does nothing if `cursor` is `last`, and never adds a zero-length segment.
     fn add_segment(&mut self, cursor: Vector2<i32>) {
+        if cursor == self.last {
+            return;
+        }
         let h = (self.last.x - cursor.x).abs();
         let v = (self.last.y - cursor.y).abs();
-        match (self.segments.last(), h > v) {
-            (Some(Wire::V(_)), _) | (None, true) => {
-                let (x1, x2) = ord(self.last.x, cursor.x);
-                let wire = Wire::H(schematic::WireH {
-                    y: self.last.y,
-                    x1,
-                    x2,
-                });
-                self.last = Vector2::new(cursor.x, self.last.y);
-                self.segments.push(wire);
-            }
-            (Some(Wire::H(_)), _) | (None, false) => {
-                let (y1, y2) = ord(self.last.y, cursor.y);
-                let wire = Wire::V(schematic::WireV {
-                    x: self.last.x,
-                    y1,
-                    y2,
-                });
-                self.last = Vector2::new(self.last.x, cursor.y);
-                self.segments.push(wire);
-            }
+        let horizontal = match self.segments.last() {
+            Some(Wire::V(_)) => h != 0,
+            Some(Wire::H(_)) => v == 0,
+            None => h > v,
+        };
+        if horizontal {
+            let (x1, x2) = ord(self.last.x, cursor.x);
+            let wire = Wire::H(schematic::WireH {
+                y: self.last.y,
+                x1,
+                x2,
+            });
+            self.last = Vector2::new(cursor.x, self.last.y);
+            self.segments.push(wire);
+        } else {
+            let (y1, y2) = ord(self.last.y, cursor.y);
+            let wire = Wire::V(schematic::WireV {
+                x: self.last.x,
+                y1,
+                y2,
+            });
+            self.last = Vector2::new(self.last.x, cursor.y);
+            self.segments.push(wire);
         }
     }
 }
 
 struct Transform {
     scale: f32,
+    /// Screen position of the world origin, relative to the top-left corner of the screen or
+    /// to its center if `center_origin` is set.
     translate: Vector2<f32>,
     screen_size: Vector2<u32>,
+    center_origin: bool,
 }
 
 impl Transform {
     fn pan_zoom(&mut self, pan: Vector2<f32>, origin: Vector2<f32>, zoom: f32) {
-        self.translate = self.translate.scale(zoom) - origin.scale(zoom) + origin + pan;
+        let offset = self.offset().scale(zoom) - origin.scale(zoom) + origin + pan;
+        self.translate = offset - self.origin();
         self.scale *= zoom;
     }
 
+    /// Zooms and pans so the world rectangle from `min` to `max` fills the screen less
+    /// `margin` pixels on each side, within the zoom limits.
+    fn fit(&mut self, min: Vector2<f32>, max: Vector2<f32>, margin: f32) {
+        let screen_size: Vector2<f32> = nalgebra::convert(self.screen_size);
+        let size = max - min;
+        let scale_x = (screen_size.x - margin * 2.).max(1.) / size.x.abs();
+        let scale_y = (screen_size.y - margin * 2.).max(1.) / size.y.abs();
+        self.scale = scale_x.min(scale_y).clamp(MIN_SCALE, MAX_SCALE);
+        let center = (min + max).scale(0.5);
+        self.translate = screen_size.scale(0.5) - center.scale(self.scale) - self.origin();
+    }
+
+    /// Screen position `translate` is relative to.
+    fn origin(&self) -> Vector2<f32> {
+        if self.center_origin {
+            nalgebra::convert::<_, Vector2<f32>>(self.screen_size).scale(0.5)
+        } else {
+            Vector2::zeros()
+        }
+    }
+
+    /// Screen position of the world origin.
+    fn offset(&self) -> Vector2<f32> {
+        self.translate + self.origin()
+    }
+
+    /// Changes the screen size keeping the world point at the center of the screen in place.
+    fn resize(&mut self, screen_size: Vector2<u32>) {
+        if screen_size == self.screen_size {
+            return;
+        }
+        // the initial 1x1 size is only a placeholder until the first frame
+        let placeholder = self.screen_size.x <= 1 || self.screen_size.y <= 1;
+        let half = |size: Vector2<u32>| nalgebra::convert::<_, Vector2<f32>>(size).scale(0.5);
+        let center = self.screen_to_world(half(self.screen_size));
+        self.screen_size = screen_size;
+        if !placeholder {
+            let offset = half(screen_size) - center.scale(self.scale);
+            self.translate = offset - self.origin();
+        }
+    }
+
     fn screen_to_world(&self, screen: Vector2<f32>) -> Vector2<f32> {
-        (screen - self.translate).unscale(self.scale)
+        (screen - self.offset()).unscale(self.scale)
     }
 
     #[allow(dead_code)]
     fn world_to_screen(&self, world: Vector2<f32>) -> Vector2<f32> {
-        world.scale(self.scale) + self.translate
+        world.scale(self.scale) + self.offset()
     }
 
     fn viewbox(&self) -> (Vector2<f32>, Vector2<f32>) {
@@ -112,10 +304,41 @@ impl Default for Transform {
             scale: 1.,
             translate: Vector2::zeros(),
             screen_size: Vector2::new(1, 1),
+            center_origin: false,
         }
     }
 }
 
+fn drag_offset(wire: Line<[i32; 2]>, grab: Vector2<i32>, cursor: Vector2<i32>) -> i32 {
+    let d = cursor - grab;
+    if schematic::Horizontal::is_para(wire) {
+        d.y
+    } else {
+        d.x
+    }
+}
+
+#[cfg(feature = "wasm")]
+fn to_js_value<T: Serialize>(value: &T) -> JsValue {
+    js_sys::JSON::parse(&serde_json::to_string(value).unwrap()).unwrap()
+}
+
+/// Asks the user for a string, or `None` if they cancel or there is no one to ask.
+fn prompt(message: &str, default: &str) -> Option<String> {
+    #[cfg(feature = "wasm")]
+    {
+        web_sys::window()?
+            .prompt_with_message_and_default(message, default)
+            .ok()
+            .flatten()
+    }
+    #[cfg(not(feature = "wasm"))]
+    {
+        let _ = (message, default);
+        None
+    }
+}
+
 #[inline]
 fn ord(v1: i32, v2: i32) -> (i32, i32) {
     if v1 <= v2 {
@@ -125,10 +348,9 @@ fn ord(v1: i32, v2: i32) -> (i32, i32) {
     }
 }
 
-#[wasm_bindgen]
 impl Cad {
-    #[wasm_bindgen(constructor)]
-    pub fn new(backend: GlowBackend) -> Self {
+    /// A `Cad` drawing through `backend`, e.g. a `RecordingBackend` outside the browser.
+    pub fn with_backend(backend: Box<dyn Backend>) -> Self {
         let draw_list = DrawList::new(Vector2::new(0, 0));
         Self {
             backend,
@@ -140,21 +362,426 @@ impl Cad {
             tool_state: ToolState::Selection,
             sch_state: schematic::State::default(),
             circuit: None,
+            sim_monitor: None,
+            rubber_band: true,
+            text_height: 50.,
+            label_halo: true,
+            zoom_sensitivity: 1.,
+            pan_sensitivity: 1.,
+            invert_wheel: false,
+            invert_zoom: false,
+            selection: vec![],
+            snap_guides: true,
+            net_cache: None,
+            highlighted_net: None,
+            render_style: RenderStyle::default(),
+            probes: vec![],
+            last_import_report: ImportReport::default(),
+            modifiers: io::Modifiers::default(),
+            held_keys: io::HeldKeys::default(),
+            primary_button: 0,
+            secondary_button: 2,
+            read_only: false,
+            sim_tick: 0,
+            auto_connect: false,
+            theme: Theme::default(),
+            stats_cache: None,
+            has_area: true,
+            units: Units::Grid,
+            units_per_grid: 1.,
+            prompt,
+        }
+    }
+
+    /// Adds `components` as `import_components` does, searching up to `max_steps` grid steps
+    /// for a free spot.
+    fn import(&mut self, components: Vec<schematic::Component>, max_steps: i32) -> usize {
+        let mut report = ImportReport::default();
+        let grid_size = self.grid_size as i32;
+        for mut component in components {
+            component.id = 0;
+            let label = component.label.clone();
+            let requested = component.position;
+            let position = (requested.x, requested.y);
+            match self
+                .sch_state
+                .add_component_nearest_free(component, grid_size, max_steps)
+            {
+                Some(placed) => {
+                    report.placed += 1;
+                    if placed != requested {
+                        report.moved.push(ImportedComponent {
+                            label,
+                            position,
+                            placed_at: Some((placed.x, placed.y)),
+                            reason: None,
+                        });
+                    }
+                }
+                None => report.rejected.push(ImportedComponent {
+                    label,
+                    position,
+                    placed_at: None,
+                    reason: Some("overlap".to_string()),
+                }),
+            }
+        }
+        let placed = report.placed;
+        self.last_import_report = report;
+        placed
+    }
+
+    /// Switches to `sch_state` as loaded from a file, dropping everything that refers to the
+    /// previous schematic. Ids are reassigned, so the selection goes too.
+    fn replace_schematic(&mut self, sch_state: schematic::State) {
+        self.sch_state = sch_state;
+        self.sch_state.assign_ids();
+        self.sch_state.dedup_wires();
+        self.net_cache = None;
+        self.stats_cache = None;
+        self.highlighted_net = None;
+        self.selection.clear();
+    }
+
+    /// Gives the component at each position its label, as `apply_labels` does. `None` while
+    /// simulating.
+    fn relabel(&mut self, labels: Vec<(Vector2<i32>, String)>) -> Option<u32> {
+        if self.circuit.is_some() {
+            return None;
+        }
+        let mut applied = 0;
+        for (position, label) in labels {
+            if let Some(component) = self
+                .sch_state
+                .components_iter_mut(rstar::AABB::from_point(position.into()))
+                .find(|component| component.position == position)
+            {
+                component.label = label;
+                applied += 1;
+            }
+        }
+        Some(applied)
+    }
+
+    /// The states after each of `steps` simulation passes, in the order of the monitor's
+    /// names, or `None` when not simulating.
+    #[cfg_attr(not(feature = "wasm"), allow(dead_code))]
+    fn trace(&mut self, steps: u32) -> Option<Vec<Vec<Option<bool>>>> {
+        if self.circuit.is_none() || self.sim_monitor.is_none() {
+            return None;
+        }
+        let mut trace = vec![];
+        for _ in 0..steps {
+            self.step_simulation();
+            if let (Some(circuit), Some(sim_monitor)) = (&self.circuit, &self.sim_monitor) {
+                trace.push(sim_monitor.snapshot(circuit));
+            }
+        }
+        Some(trace)
+    }
+
+    #[cfg_attr(not(feature = "wasm"), allow(dead_code))]
+    fn components_in(&self, corner1: [i32; 2], corner2: [i32; 2]) -> Vec<ComponentMetadata> {
+        let aabb = rstar::AABB::from_corners(corner1, corner2);
+        self.sch_state
+            .components_iter(aabb)
+            .map(ComponentMetadata::from)
+            .collect()
+    }
+
+    /// Netlist statistics, recomputed only after the schematic changes.
+    pub fn cached_netlist_stats(&mut self) -> &schematic::NetlistStats {
+        let revision = self.sch_state.revision();
+        match &self.stats_cache {
+            Some((cached, _)) if *cached == revision => {}
+            _ => self.stats_cache = Some((revision, self.sch_state.netlist_stats())),
+        }
+        &self.stats_cache.as_ref().unwrap().1
+    }
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl Cad {
+    #[wasm_bindgen(constructor)]
+    pub fn new(backend: GlowBackend) -> Self {
+        Self::with_backend(Box::new(backend))
+    }
+
+    /// Passes `{ level, message }` of every log record to `handler` instead of the console.
+    /// `undefined` restores the console.
+    pub fn set_log_handler(&mut self, handler: Option<js_sys::Function>) {
+        let sink = handler.map(|handler| {
+            Box::new(move |record: &crate::logger::Record| {
+                let _ = handler.call1(&JsValue::NULL, &to_js_value(record));
+            }) as Box<dyn Fn(&crate::logger::Record)>
+        });
+        crate::logger::set_sink(sink);
+    }
+
+    /// `{ placed, moved, rejected }` for the last `import_components`.
+    pub fn last_import_report(&self) -> JsValue {
+        to_js_value(&self.last_import_report)
+    }
+
+    /// Components intersecting the rectangle spanned by the two corners, in world units,
+    /// as an array of `ComponentMetadata`.
+    pub fn components_in_rect(&self, x1: i32, y1: i32, x2: i32, y2: i32) -> JsValue {
+        to_js_value(&self.components_in([x1, y1], [x2, y2]))
+    }
+
+    /// Every component, ordered by label and position, as `ComponentMetadata` plus
+    /// `pads: [{ name, position, connected }]`, for flagging parts with floating pads.
+    pub fn list_components(&self) -> JsValue {
+        #[derive(Serialize)]
+        struct Entry {
+            #[serde(flatten)]
+            component: ComponentMetadata,
+            pads: Vec<schematic::PadConnection>,
+        }
+        let components = self
+            .sch_state
+            .components_sorted()
+            .into_iter()
+            .map(|component| Entry {
+                component: ComponentMetadata::from(component),
+                pads: self.sch_state.pad_connections(component),
+            })
+            .collect::<Vec<_>>();
+        to_js_value(&components)
+    }
+
+    /// `{ nets, relays, switches, floating_pads, shorted_switches }`, recomputed only after
+    /// the schematic changes.
+    pub fn netlist_stats(&mut self) -> JsValue {
+        to_js_value(self.cached_netlist_stats())
+    }
+
+    /// Design rule violations as an array of
+    /// `{ severity, kind, message, position, component? }`.
+    pub fn drc(&self) -> JsValue {
+        to_js_value(&self.sch_state.drc())
+    }
+
+    /// Saves like `save_schematic` and checks the design rules at the same time, returning
+    /// `{ json, issues }` so the host can warn before the file is shared.
+    pub fn save_with_drc(&self) -> JsValue {
+        #[derive(Serialize)]
+        struct Checked {
+            json: String,
+            issues: Vec<schematic::Issue>,
+        }
+        to_js_value(&Checked {
+            json: self.save_schematic(),
+            issues: self.sch_state.drc(),
+        })
+    }
+
+    /// Every net as `{ id, points, pads: [{ component, label, pad }] }`.
+    pub fn list_nets(&self) -> JsValue {
+        to_js_value(&self.sch_state.nets())
+    }
+
+    /// Runs `steps` simulation passes and returns `{ names, steps }` where each step is the
+    /// list of states after that pass, in the order of `names`. Null when not simulating.
+    pub fn simulate_trace(&mut self, steps: u32) -> JsValue {
+        #[derive(Serialize)]
+        struct Trace<'a> {
+            names: &'a [String],
+            steps: Vec<Vec<Option<bool>>>,
+        }
+        match self.trace(steps) {
+            Some(trace) => to_js_value(&Trace {
+                names: self.sim_monitor.as_ref().unwrap().names(),
+                steps: trace,
+            }),
+            None => JsValue::NULL,
+        }
+    }
+
+    /// `{ passes, converged }` for the simulation since it started or since the last
+    /// switch was flipped, or null when not simulating.
+    pub fn sim_metrics(&self) -> JsValue {
+        match &self.sim_monitor {
+            Some(sim_monitor) => to_js_value(&sim_monitor.metrics()),
+            None => JsValue::NULL,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Cad {
+    /// Whether wires attached to a dragged segment follow it.
+    pub fn set_rubber_band(&mut self, enabled: bool) {
+        self.rubber_band = enabled;
+    }
+
+    /// Height of labels in world units. One grid step by default.
+    pub fn set_text_height(&mut self, text_height: f32) {
+        self.text_height = text_height;
+    }
+
+    /// `"dot"` (default) draws wires crossing without connecting plainly, `"hop"` makes the
+    /// horizontal one hop over the vertical one.
+    pub fn set_crossing_style(&mut self, style: &str) -> Result<(), JsValue> {
+        self.render_style.crossing_style = match style {
+            "dot" => CrossingStyle::Dot,
+            "hop" => CrossingStyle::Hop,
+            _ => return Err(JsValue::from_str(&format!("unknown crossing style: {}", style))),
+        };
+        Ok(())
+    }
+
+    /// Shows coordinates and lengths in `"grid"` steps, `"mm"` or `"in"`, one grid step being
+    /// `per_grid` of them.
+    pub fn set_units(&mut self, kind: &str, per_grid: f32) -> Result<(), JsValue> {
+        let units = match kind {
+            "grid" => Units::Grid,
+            "mm" => Units::Millimeter,
+            "in" => Units::Inch,
+            _ => return Err(JsValue::from_str(&format!("unknown units: {}", kind))),
+        };
+        if !(per_grid.is_finite() && per_grid > 0.) {
+            return Err(JsValue::from_str("units per grid step must be positive"));
+        }
+        self.units = units;
+        self.units_per_grid = per_grid;
+        Ok(())
+    }
+
+    /// Formats a length in world units in the display units, e.g. for a measurement readout.
+    pub fn format_length(&self, world: f32) -> String {
+        let value = world / self.grid_size as f32 * self.units_per_grid;
+        match self.units {
+            Units::Grid => format!("{}", value),
+            Units::Millimeter => format!("{:.2} mm", value),
+            Units::Inch => format!("{:.3} in", value),
+        }
+    }
+
+    /// The grid point under the pointer in the display units, as `"x, y"`.
+    pub fn cursor_position(&self) -> String {
+        format!(
+            "{}, {}",
+            self.format_length(self.cursor.x as f32),
+            self.format_length(self.cursor.y as f32)
+        )
+    }
+
+    /// Whether wire corners and tees are drawn rounded. Off by default.
+    pub fn set_round_wire_corners(&mut self, enabled: bool) {
+        self.render_style.round_wire_corners = enabled;
+    }
+
+    /// Replaces the colors with a JSON `Theme`, e.g. `{ "selection": [0, 0.4, 1, 0.8] }`.
+    /// Colors left out get their defaults.
+    pub fn set_theme(&mut self, json: &str) -> Result<(), JsValue> {
+        self.theme = serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(())
+    }
+
+    /// Whether a placed component's pad one grid step from a dangling wire end gets connected
+    /// by extending the wire. Off by default.
+    pub fn set_auto_connect(&mut self, enabled: bool) {
+        self.auto_connect = enabled;
+    }
+
+    /// Disables editing, leaving navigation and simulation, for embedding a finished
+    /// schematic.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+        if read_only {
+            self.tool_state = ToolState::Selection;
         }
     }
 
+    /// Mouse button (as in `MouseEvent.button`) that places, wires and toggles. 0 by default.
+    pub fn set_primary_button(&mut self, button: u8) {
+        self.primary_button = button;
+    }
+
+    /// Mouse button that cancels placing or wiring. 2 by default.
+    pub fn set_secondary_button(&mut self, button: u8) {
+        self.secondary_button = button;
+    }
+
+    /// Whether the view translation is measured from the center of the screen instead of its
+    /// top-left corner, so the view stays centered when the canvas is resized. The view
+    /// doesn't move when this is toggled.
+    pub fn set_center_origin(&mut self, enabled: bool) {
+        let offset = self.transform.offset();
+        self.transform.center_origin = enabled;
+        self.transform.translate = offset - self.transform.origin();
+    }
+
+    /// Draws wires and symbols `px` screen pixels wide regardless of zoom, e.g. `1` for
+    /// hairlines when printing. `undefined` goes back to their world-unit thickness.
+    pub fn set_screen_stroke(&mut self, px: Option<f32>) {
+        self.render_style.screen_stroke = px;
+    }
+
+    /// Scales how finely circles and junction dots are tessellated; `1` is the default,
+    /// lower is cheaper, higher is smoother.
+    pub fn set_circle_quality(&mut self, quality: f32) {
+        self.draw_list.circle_quality = quality.max(0.1);
+    }
+
+    /// Whether labels get a background-colored plate so they stay readable over wires.
+    pub fn set_label_halo(&mut self, enabled: bool) {
+        self.label_halo = enabled;
+    }
+
+    /// Multiplier for pinch zoom. 1.0 by default.
+    pub fn set_zoom_sensitivity(&mut self, f: f32) {
+        self.zoom_sensitivity = f;
+    }
+
+    /// Multiplier for wheel pan. 1.0 by default.
+    pub fn set_pan_sensitivity(&mut self, f: f32) {
+        self.pan_sensitivity = f;
+    }
+
+    /// Flips the wheel pan direction.
+    pub fn set_invert_wheel(&mut self, invert: bool) {
+        self.invert_wheel = invert;
+    }
+
+    /// Flips the pinch zoom direction.
+    pub fn set_invert_zoom(&mut self, invert: bool) {
+        self.invert_zoom = invert;
+    }
+
+    /// Pans the view by screen pixels.
+    pub fn pan_by(&mut self, dx: f32, dy: f32) {
+        self.transform.translate += Vector2::new(dx, dy);
+    }
+
     fn process_pan_zoom(&mut self, io: &Io) {
-        let pan = -io.wheel;
+        let wheel_sign = if self.invert_wheel { 1. } else { -1. };
+        let zoom_sign = if self.invert_zoom { 1. } else { -1. };
+        let pan = io.wheel.scale(wheel_sign * self.pan_sensitivity);
         let origin = io.mouse;
-        let mut zoom = 1. - io.wheel_pinch * 0.02;
-        if self.transform.scale * zoom < 0.1 {
-            zoom = 0.1 / self.transform.scale;
-        } else if self.transform.scale * zoom > 16.0 {
-            zoom = 16.0 / self.transform.scale;
+        let mut zoom = 1. + zoom_sign * io.wheel_pinch * 0.02 * self.zoom_sensitivity;
+        if self.transform.scale * zoom < MIN_SCALE {
+            zoom = MIN_SCALE / self.transform.scale;
+        } else if self.transform.scale * zoom > MAX_SCALE {
+            zoom = MAX_SCALE / self.transform.scale;
         }
         self.transform.pan_zoom(pan, origin, zoom);
     }
 
+    /// Frames the world rectangle spanned by the two corners.
+    pub fn zoom_to_region(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
+        let min = Vector2::new(x1.min(x2), y1.min(y2));
+        let max = Vector2::new(x1.max(x2), y1.max(y2));
+        self.transform.fit(min, max, FIT_MARGIN);
+    }
+
+    /// Whether a moved component snaps to the rows and columns of other components.
+    pub fn set_snap_guides(&mut self, enabled: bool) {
+        self.snap_guides = enabled;
+    }
+
     fn process_cursor(&mut self, io: &Io) {
         let w = self.transform.screen_to_world(io.mouse);
         self.pointer = w.map(|f| f.round() as i32);
@@ -162,33 +789,123 @@ impl Cad {
             .unscale(self.grid_size as f32)
             .map(|f| f.round() as i32 * self.grid_size as i32);
         self.cursor = snapped;
+        if let ToolState::MovingComponent(moving) = &self.tool_state {
+            let (position, guides) = self.snap_component(&moving.component, w + moving.offset);
+            if let ToolState::MovingComponent(moving) = &mut self.tool_state {
+                self.cursor = position - moving.offset.map(|f| f.round() as i32);
+                moving.position = position;
+                moving.guides = guides;
+            }
+        }
+    }
+
+    /// Snaps `raw` to the grid, or to the row or column of a nearby component when that is
+    /// closer.
+    fn snap_component(
+        &self,
+        component: &schematic::Component,
+        raw: Vector2<f32>,
+    ) -> (Vector2<i32>, (Option<i32>, Option<i32>)) {
+        let grid_size = self.grid_size as f32;
+        let grid = raw.map(|f| ((f / grid_size).round() * grid_size) as i32);
+        if !self.snap_guides {
+            return (grid, (None, None));
+        }
+        let tolerance = SNAP_TOLERANCE / self.transform.scale;
+        let (top_left, bottom_right) = self.grid_viewbox();
+        let aabb = rstar::AABB::from_corners(top_left.into(), bottom_right.into());
+        let nearest = |grid: i32, raw: f32, coord: &dyn Fn(&schematic::Component) -> i32| {
+            self.sch_state
+                .components_iter(aabb)
+                .filter(|other| other.id != component.id)
+                .map(coord)
+                .filter(|&c| (c as f32 - raw).abs() <= tolerance)
+                .filter(|&c| (c as f32 - raw).abs() <= (grid as f32 - raw).abs())
+                .min_by_key(|&c| (c as f32 - raw).abs() as i32)
+        };
+        let guide_x = nearest(grid.x, raw.x, &|c| c.position.x);
+        let guide_y = nearest(grid.y, raw.y, &|c| c.position.y);
+        let position = Vector2::new(guide_x.unwrap_or(grid.x), guide_y.unwrap_or(grid.y));
+        (position, (guide_x, guide_y))
+    }
+
+    /// The junction nearest to the pointer within the pick tolerance, so a new wire connects to
+    /// it even if it is off the grid, or else the cursor.
+    fn wiring_start(&self) -> Vector2<i32> {
+        let tolerance = self.grid_size as i32 / 4;
+        let p = self.pointer;
+        let aabb = rstar::AABB::from_corners(
+            [p.x - tolerance, p.y - tolerance],
+            [p.x + tolerance, p.y + tolerance],
+        );
+        self.sch_state
+            .junctions_iter(&aabb)
+            .map(|(junction, _)| junction)
+            .min_by_key(|junction| (junction.x - p.x).abs() + (junction.y - p.y).abs())
+            .unwrap_or(self.cursor)
+    }
+
+    /// Asks via `prompt` for a new label of the component under the cursor, or for a new value
+    /// while simulating: the label names the simulator states, so it can't change then.
+    fn edit_component_at_cursor(&mut self, prompt: impl FnOnce(&str, &str) -> Option<String>) {
+        if self.circuit.is_some() {
+            self.edit_value_at_cursor(prompt);
+            return;
+        }
+        let aabb = rstar::AABB::from_point(self.cursor.into());
+        if let Some(comp) = self.sch_state.components_iter_mut(aabb).next() {
+            if let Some(new_label) = prompt("Label", &comp.label) {
+                comp.label = new_label;
+            }
+        }
+    }
+
+    /// Asks via `prompt` for a new value of the component under the cursor.
+    fn edit_value_at_cursor(&mut self, prompt: impl FnOnce(&str, &str) -> Option<String>) {
+        let aabb = rstar::AABB::from_point(self.cursor.into());
+        if let Some(comp) = self.sch_state.components_iter_mut(aabb).next() {
+            if let Some(new_value) = prompt("Value", &comp.value) {
+                comp.value = new_value;
+            }
+        }
     }
 
     fn process_event_tool_selection(&mut self, event: &io::Event) -> (bool, Option<ToolState>) {
         match event {
             io::Event::Keydown(key) => match key.as_str() {
-                "w" => (false, Some(ToolState::Wiring(Wiring::start(self.cursor)))),
+                "w" => (false, Some(ToolState::Wiring(Wiring::start(self.wiring_start())))),
                 "p" => (
                     false,
                     Some(ToolState::PlacingComponent(
-                        symbol::Kind::Power,
+                        symbol::Kind::POWER,
                         Default::default(),
                     )),
                 ),
                 "s" => (
                     false,
                     Some(ToolState::PlacingComponent(
-                        symbol::Kind::Contact,
+                        symbol::Kind::CONTACT,
                         Default::default(),
                     )),
                 ),
                 "c" => (
                     false,
                     Some(ToolState::PlacingComponent(
-                        symbol::Kind::Coil,
+                        symbol::Kind::COIL,
+                        Default::default(),
+                    )),
+                ),
+                "t" => (
+                    false,
+                    Some(ToolState::PlacingComponent(
+                        symbol::Kind::DPDT,
                         Default::default(),
                     )),
                 ),
+                "v" => {
+                    self.edit_value_at_cursor(self.prompt);
+                    (false, None)
+                }
                 "d" => {
                     self.sch_state
                         .delete_at_point(self.pointer.into(), self.grid_size as i32 / 4);
@@ -204,30 +921,70 @@ impl Cad {
                         .mirror_component_at_point(self.pointer, self.grid_size as i32 / 4);
                     (false, None)
                 }
-                _ => (true, None),
-            },
-            io::Event::DoubleClick(0) => {
-                if self.circuit.is_some() {
-                    return (false, None);
+                "m" => {
+                    let component = self
+                        .sch_state
+                        .components_iter(rstar::AABB::from_point(self.pointer.into()))
+                        .next()
+                        .cloned();
+                    if let Some(component) = component {
+                        let pointer: Vector2<f32> = nalgebra::convert(self.pointer);
+                        let position: Vector2<f32> = nalgebra::convert(component.position);
+                        let offset = position - pointer;
+                        let position = component.position;
+                        return (
+                            false,
+                            Some(ToolState::MovingComponent(MovingComponent {
+                                component,
+                                offset,
+                                position,
+                                guides: (None, None),
+                            })),
+                        );
+                    }
+                    let wire = self
+                        .sch_state
+                        .wire_at_point(self.pointer.into(), self.grid_size as i32 / 4);
+                    (
+                        false,
+                        wire.map(|wire| ToolState::DraggingWire(wire, self.cursor)),
+                    )
                 }
-                if let Some(comp) = self.sch_state.components_iter_mut(rstar::AABB::from_point(self.cursor.into())).next() {
-                    if let Some(new_label) = web_sys::window().unwrap().prompt_with_message_and_default("Label", &comp.label).unwrap() {
-                        comp.label = new_label;
+                // Arrow keys pan the view unless the pointer is over a component, which is left
+                // for editing the component itself. Holding Shift pans regardless.
+                "ArrowLeft" | "ArrowRight" | "ArrowUp" | "ArrowDown" => {
+                    let over_component = !self.modifiers.shift
+                        && self
+                        .sch_state
+                        .components_iter(rstar::AABB::from_point(self.pointer.into()))
+                        .next()
+                        .is_some();
+                    if over_component {
+                        return (true, None);
                     }
+                    let (dx, dy) = match key.as_str() {
+                        "ArrowLeft" => (KEY_PAN_STEP, 0.),
+                        "ArrowRight" => (-KEY_PAN_STEP, 0.),
+                        "ArrowUp" => (0., KEY_PAN_STEP),
+                        _ => (0., -KEY_PAN_STEP),
+                    };
+                    self.pan_by(dx, dy);
+                    (false, None)
                 }
+                _ => (true, None),
+            },
+            io::Event::DoubleClick(button) if *button == self.primary_button => {
+                self.edit_component_at_cursor(self.prompt);
                 (false, None)
             },
-            io::Event::Click(0) => {
-                if let Some(circuit) = &mut self.circuit {
-                    if let Some(component) = self.sch_state.components_iter(rstar::AABB::from_point(self.cursor.into())).next() {
-                        if component.symbol == symbol::Kind::Contact {
-                            let state = circuit.get_state(&format!("{}.A", &component.label)).unwrap_or(false);
-                            let a = !state;
-                            let b = !a;
-                            circuit.set_state(&format!("{}.A", &component.label), a);
-                            circuit.set_state(&format!("{}.B", &component.label), b);
-                        }
-                    }
+            io::Event::Click(button) if *button == self.primary_button => {
+                let id = self
+                    .sch_state
+                    .components_iter(rstar::AABB::from_point(self.cursor.into()))
+                    .next()
+                    .map(|component| component.id);
+                if let Some(id) = id {
+                    self.toggle_contact(id);
                 }
                 (false, None)
             },
@@ -237,7 +994,12 @@ impl Cad {
 
     fn process_event_tool_ready_to_wire(&mut self, event: &io::Event) -> (bool, Option<ToolState>) {
         match event {
-            io::Event::Click(0) => (false, Some(ToolState::Wiring(Wiring::start(self.cursor)))),
+            io::Event::Click(button) if *button == self.primary_button => {
+                (false, Some(ToolState::Wiring(Wiring::start(self.wiring_start()))))
+            }
+            io::Event::Keydown(key) if key == "w" => {
+                (false, Some(ToolState::Wiring(Wiring::start(self.wiring_start()))))
+            }
             _ => (true, None),
         }
     }
@@ -252,11 +1014,14 @@ impl Cad {
                 wiring.add_segment(self.cursor);
                 (false, None)
             }
-            io::Event::Click(0) => {
+            io::Event::Click(button) if *button == self.primary_button => {
                 wiring.add_segment(self.cursor);
                 (false, None)
             }
-            io::Event::DoubleClick(0) => {
+            io::Event::Click(button) if *button == self.secondary_button => {
+                (false, Some(ToolState::Selection))
+            }
+            io::Event::DoubleClick(button) if *button == self.primary_button => {
                 for wire in &wiring.segments {
                     match wire {
                         Wire::H(wire_h) => {
@@ -280,36 +1045,23 @@ impl Cad {
         rot_mirror: &mut schematic::RotMirror,
     ) -> (bool, Option<ToolState>) {
         match event {
-            io::Event::Click(0) => {
-                match symbol {
-                    symbol::Kind::Power => {
-                        let power = schematic::Component::new(
-                            self.cursor,
-                            symbol::Kind::Power,
-                            *rot_mirror,
-                            "V+".to_string(),
-                        );
-                        self.sch_state.add_component(power);
-                    }
-                    symbol::Kind::Contact => {
-                        let contact = schematic::Component::new(
-                            self.cursor,
-                            symbol::Kind::Contact,
-                            *rot_mirror,
-                            "R".to_string(),
-                        );
-                        self.sch_state.add_component(contact);
-                    }
-                    symbol::Kind::Coil => {
-                        let coil = schematic::Component::new(
-                            self.cursor,
-                            symbol::Kind::Coil,
-                            *rot_mirror,
-                            "R".to_string(),
-                        );
-                        self.sch_state.add_component(coil);
-                    }
-                }
+            io::Event::Click(button) if *button == self.primary_button => {
+                let component = schematic::Component::new(
+                    self.cursor,
+                    *symbol,
+                    *rot_mirror,
+                    symbol.def().default_label().to_string(),
+                );
+                let placed = if self.auto_connect {
+                    self.sch_state
+                        .add_component_auto_connect(component, self.grid_size as i32)
+                } else {
+                    self.sch_state.add_component(component)
+                };
+                // on top of another component, keep placing
+                (false, placed.map(|_| ToolState::Selection))
+            }
+            io::Event::Click(button) if *button == self.secondary_button => {
                 (false, Some(ToolState::Selection))
             }
             io::Event::Keydown(key) if key == "r" => {
@@ -328,7 +1080,51 @@ impl Cad {
         }
     }
 
+    fn process_event_tool_dragging_wire(
+        &mut self,
+        event: &io::Event,
+        wire: Line<[i32; 2]>,
+        grab: Vector2<i32>,
+    ) -> (bool, Option<ToolState>) {
+        match event {
+            io::Event::Click(button) if *button == self.primary_button => {
+                let offset = drag_offset(wire, grab, self.cursor);
+                self.sch_state.drag_wire(wire, offset, self.rubber_band);
+                (false, Some(ToolState::Selection))
+            }
+            _ => (true, None),
+        }
+    }
+
+    fn process_event_tool_moving_component(
+        &mut self,
+        event: &io::Event,
+        moving: &MovingComponent,
+    ) -> (bool, Option<ToolState>) {
+        match event {
+            io::Event::Click(button) if *button == self.primary_button => {
+                self.sch_state
+                    .move_component(moving.component.id, moving.position);
+                (false, Some(ToolState::Selection))
+            }
+            _ => (true, None),
+        }
+    }
+
+    /// Whether `event` may reach the tools in read-only mode: panning, and clicks for
+    /// toggling contacts.
+    fn allowed_in_read_only(&self, event: &io::Event) -> bool {
+        match event {
+            io::Event::Keydown(key) => key.starts_with("Arrow"),
+            io::Event::Click(button) => *button == self.primary_button,
+            _ => false,
+        }
+    }
+
     fn process_event_tool(&mut self, event: &io::Event) -> bool {
+        if self.read_only && !self.allowed_in_read_only(event) {
+            return true;
+        }
         let mut tool_state = std::mem::replace(&mut self.tool_state, ToolState::Selection);
         let (prevent_default, next_state) = match &mut tool_state {
             ToolState::Selection => self.process_event_tool_selection(event),
@@ -337,6 +1133,12 @@ impl Cad {
             ToolState::PlacingComponent(symbol, rot_mirror) => {
                 self.process_event_tool_placing_component(event, symbol, rot_mirror)
             }
+            ToolState::DraggingWire(wire, grab) => {
+                self.process_event_tool_dragging_wire(event, *wire, *grab)
+            }
+            ToolState::MovingComponent(moving) => {
+                self.process_event_tool_moving_component(event, moving)
+            }
         };
         if let Some(next_state) = next_state {
             self.tool_state = next_state;
@@ -347,6 +1149,10 @@ impl Cad {
     }
 
     fn process_event(&mut self, event: &io::Event) {
+        self.modifiers.update(event);
+        if !self.held_keys.update(event) {
+            return;
+        }
         if !self.process_event_tool(event) {
             return;
         }
@@ -354,6 +1160,10 @@ impl Cad {
             io::Event::Keydown(key) if key == "Escape" => {
                 self.tool_state = ToolState::Selection;
             }
+            // only reached when no tool consumed the key
+            io::Event::Keydown(key) if key == " " => {
+                self.toggle_simulation();
+            }
             _ => {}
         }
     }
@@ -365,17 +1175,31 @@ impl Cad {
     }
 
     pub fn new_frame(&mut self, io: &mut Io) {
-        self.transform.screen_size = io.screen_size;
+        // the canvas may have no area before layout; keep the math finite and draw nothing
+        self.has_area = io.screen_size.x > 0 && io.screen_size.y > 0;
+        self.transform.resize(io.screen_size.map(|n| n.max(1)));
         let pixel_ratio = io.pixel_ratio;
         self.process_pan_zoom(io);
         self.process_cursor(io);
         self.process_events(io);
         io.reset();
         self.draw_list.clear();
+        let glyph_count = self
+            .sch_state
+            .components()
+            .map(|c| c.label.chars().count())
+            .sum();
+        self.draw_list.reserve_for(
+            self.sch_state.wire_count(),
+            self.sch_state.component_count(),
+            glyph_count,
+        );
         self.draw_list.pixel_ratio = pixel_ratio;
         self.draw_list.scale = self.transform.scale;
         self.draw_list.translate = self.transform.translate;
+        self.draw_list.center_origin = self.transform.center_origin;
         self.draw_list.screen_size = self.transform.screen_size;
+        self.draw_list.bg_color = self.theme.background;
     }
 
     fn draw_grid(&mut self) {
@@ -402,7 +1226,7 @@ impl Cad {
 
     fn draw_cursor(&mut self) {
         let p: Vector2<f32> = nalgebra::convert(self.cursor);
-        let col = Color::new(0., 0., 0., 1.);
+        let col = self.theme.cursor;
         let thickness = 1.0 / self.transform.scale;
         let half_len = 35. / self.transform.scale;
         self.draw_list.add_line(
@@ -419,21 +1243,94 @@ impl Cad {
         );
     }
 
+    /// Draws the schematic on top of the grid, one layer each for wires, junctions,
+    /// components and labels in that order.
     fn draw_schematic(&mut self) {
-        let sch_state = std::mem::take(&mut self.sch_state);
         let (top_left, right_bottom) = self.grid_viewbox();
         let aabb = rstar::AABB::from_corners(top_left.into(), right_bottom.into());
+        if !self.sch_state.any_in(&aabb) {
+            return;
+        }
+        if self.highlighted_net.is_some() {
+            self.refresh_net_cache();
+        }
+        let net_cache = self.net_cache.take();
+        let highlighted = self
+            .highlighted_net
+            .and_then(|net| net_cache.as_ref().map(|(_, net_map)| (net, net_map)));
+        let sch_state = std::mem::take(&mut self.sch_state);
+        self.draw_list.new_layer();
         for wire in sch_state.wires_iter(&aabb) {
-            self.wire(wire.from.into(), wire.to.into());
+            match highlighted {
+                Some((net, net_map)) if net_map.get(&wire.from) == Some(&net) => {
+                    let col = self.theme.highlight;
+                    self.wire_with_color(wire.from.into(), wire.to.into(), col);
+                }
+                _ => self.wire(wire.from.into(), wire.to.into()),
+            }
         }
-        for component in sch_state.components_iter(aabb) {
-            self.component(component);
-            self.text((component.position + Vector2::new(50, 0)).map(|n| n as f32), &component.label);
+        self.net_cache = net_cache;
+        if self.render_style.crossing_style == CrossingStyle::Hop {
+            self.draw_list.new_layer();
+            for p in sch_state.crossings(&aabb) {
+                self.crossing_hop(p.into());
+            }
         }
+        self.draw_list.new_layer();
         for (p, rc) in sch_state.junctions_iter(&aabb) {
             self.junction(p, rc);
         }
-        self.sch_state = sch_state;
+        self.draw_list.new_layer();
+        for component in sch_state.components_iter(aabb) {
+            self.component(component);
+        }
+        // the value goes on the line below the label
+        let line_height = self.text_height * 1.2;
+        let texts = sch_state
+            .components_iter(aabb)
+            .flat_map(|component| {
+                let p = (component.position + Vector2::new(50, 0)).map(|n| n as f32);
+                let value = Some((p + Vector2::new(0., line_height), component.value.as_str()))
+                    .filter(|(_, value)| !value.is_empty());
+                std::iter::once((p, component.label.as_str())).chain(value)
+            })
+            .collect::<Vec<_>>();
+        if self.label_halo {
+            self.draw_list.new_layer();
+            for &(p, text) in &texts {
+                self.text_halo(p, text);
+            }
+        }
+        self.draw_list.new_layer();
+        for &(p, text) in &texts {
+            self.text(p, text);
+        }
+        self.sch_state = sch_state;
+    }
+
+    fn draw_selection(&mut self) {
+        let col = self.theme.selection;
+        let thickness = 2. / self.transform.scale;
+        let selection = &self.selection;
+        let aabbs = self
+            .sch_state
+            .components()
+            .filter(|c| selection.contains(&c.id))
+            .map(|c| c.envelope())
+            .collect::<Vec<_>>();
+        for aabb in aabbs {
+            let [x1, y1] = aabb.lower();
+            let [x2, y2] = aabb.upper();
+            let corners: [Vector2<f32>; 4] = [
+                nalgebra::convert(Vector2::new(x1, y1)),
+                nalgebra::convert(Vector2::new(x2, y1)),
+                nalgebra::convert(Vector2::new(x2, y2)),
+                nalgebra::convert(Vector2::new(x1, y2)),
+            ];
+            for i in 0..4 {
+                self.draw_list.add_line(corners[i], corners[(i + 1) % 4], col, thickness);
+            }
+        }
     }
 
     fn grid_viewbox(&self) -> (Vector2<i32>, Vector2<i32>) {
@@ -444,17 +1341,60 @@ impl Cad {
     }
 
     fn wire(&mut self, p1: Vector2<i32>, p2: Vector2<i32>) {
+        self.wire_with_color(p1, p2, self.theme.wire);
+    }
+
+    fn wire_with_color(&mut self, p1: Vector2<i32>, p2: Vector2<i32>, col: Color) {
+        if p1 == p2 {
+            return;
+        }
         let p1 = nalgebra::convert(p1);
         let p2 = nalgebra::convert(p2);
-        let col = Color::new(0., 132. / 255., 0., 1.);
-        self.draw_list.add_line(p1, p2, col, 6.);
+        let thickness = self.stroke(6.);
+        self.draw_list.add_line(p1, p2, col, thickness);
+    }
+
+    /// `thickness` in world units, or the screen stroke converted to world units if set.
+    fn stroke(&self, thickness: f32) -> f32 {
+        match self.render_style.screen_stroke {
+            Some(px) => px / self.transform.scale,
+            None => thickness,
+        }
+    }
+
+    /// Recomputes the point to net map if the schematic was edited since.
+    fn refresh_net_cache(&mut self) {
+        let revision = self.sch_state.revision();
+        match &self.net_cache {
+            Some((cached, _)) if *cached == revision => {}
+            _ => self.net_cache = Some((revision, self.sch_state.connectivity())),
+        }
+    }
+
+    /// Cuts the horizontal wire around the crossing at `p` and bridges the gap with an arc.
+    fn crossing_hop(&mut self, p: Vector2<i32>) {
+        let p: Vector2<f32> = nalgebra::convert(p);
+        let r = self.grid_size as f32 / 5.;
+        let thickness = self.stroke(6.);
+        let gap = Vector2::new(r, 0.);
+        let reach = Vector2::new(0., r + thickness);
+        let bg = self.draw_list.bg_color;
+        self.draw_list.add_line(p - gap, p + gap, bg, thickness * 1.5);
+        self.draw_list.add_line(p - reach, p + reach, self.theme.wire, thickness);
+        let pi = std::f32::consts::PI;
+        self.draw_list.add_arc(p, r, pi, 2. * pi, self.theme.wire, thickness);
     }
 
     fn junction(&mut self, p: Vector2<i32>, rc: u8) {
         let p = nalgebra::convert(p);
-        let col = Color::new(0., 132. / 255., 0., 1.);
+        let col = self.theme.wire;
+        if self.render_style.round_wire_corners && rc >= 2 {
+            // as wide as the wire, which may be a screen stroke
+            self.draw_list.add_disc(p, self.stroke(6.) * 0.5, col);
+        }
         if rc >= 3 {
-            self.draw_list.add_line(p, p, col, 40.);
+            // keeps its size relative to the wire
+            self.draw_list.add_line(p, p, col, self.stroke(6.) * (40. / 6.));
         } else if rc == 1 {
             self.draw_list.add_circle(p, 10., col, 1.);
         }
@@ -463,39 +1403,35 @@ impl Cad {
     fn component(&mut self, component: &schematic::Component) {
         let rot_mirror = component.rot_mirror;
         let position = component.position;
-        let col = Color::new(0.51, 0., 0., 1.);
-        match component.symbol {
-            symbol::Kind::Power => {
-                let draw_iter = symbol::power::DRAW
-                    .iter()
-                    .map(|draw| draw.transform(rot_mirror, position));
-                self.draw_symbol(col, draw_iter);
-            }
-            symbol::Kind::Contact => {
-                let a = self.circuit.as_ref().and_then(|c| c.get_state(&format!("{}.A", &component.label))).unwrap_or(false);
-                let b = self.circuit.as_ref().and_then(|c| c.get_state(&format!("{}.B", &component.label))).unwrap_or(true);
-                let draw_iter =
-                    symbol::contact::draw(a, b).map(|draw| draw.transform(rot_mirror, position));
-                self.draw_symbol(col, draw_iter);
-            }
-            symbol::Kind::Coil => {
-                let a = self.circuit.as_ref().and_then(|c| c.get_state(&format!("{}.A", &component.label))).unwrap_or(false);
-                let draw_iter =
-                    symbol::coil::draw(a).map(|draw| draw.transform(rot_mirror, position));
-                self.draw_symbol(col, draw_iter);
-            }
-        }
+        let col = self.theme.component;
+        let circuit = self.circuit.as_ref();
+        let draws = component.symbol.def().draw(&|name| {
+            circuit.and_then(|c| c.get_state(&format!("{}.{}", component.label, name)))
+        });
+        let draw_iter = draws
+            .iter()
+            .map(|draw| draw.transform(rot_mirror, position));
+        self.draw_symbol(col, draw_iter);
     }
 
     fn draw_symbol(&mut self, col: Color, draw_iter: impl Iterator<Item = symbol::Draw>) {
         for draw in draw_iter {
             match draw {
                 symbol::Draw::Line(p1, p2, thickness) => {
+                    let thickness = self.stroke(thickness);
                     self.draw_list.add_line(p1, p2, col, thickness);
                 }
                 symbol::Draw::Circle(p, r, thickness) => {
+                    let thickness = self.stroke(thickness);
                     self.draw_list.add_circle(p, r, col, thickness);
                 }
+                symbol::Draw::FilledPolygon(points) => {
+                    self.draw_list.add_filled_polygon(&points, col);
+                }
+                symbol::Draw::Arc(p, r, start, end, thickness) => {
+                    let thickness = self.stroke(thickness);
+                    self.draw_list.add_arc(p, r, start, end, col, thickness);
+                }
             }
         }
     }
@@ -538,49 +1474,199 @@ impl Cad {
 
     fn draw_placing_component(&mut self, symbol: symbol::Kind, rot_mirror: schematic::RotMirror) {
         let position = self.cursor;
-        let col = Color::new(0.51, 0., 0., 0.5);
-        match symbol {
-            symbol::Kind::Power => {
-                let draw_iter = symbol::power::DRAW
-                    .iter()
-                    .map(|draw| draw.transform(rot_mirror, position));
-                self.draw_symbol(col, draw_iter);
-            }
-            symbol::Kind::Contact => {
-                let draw_iter =
-                    symbol::contact::draw(false, true).map(|draw| draw.transform(rot_mirror, position));
-                self.draw_symbol(col, draw_iter);
-            }
-            symbol::Kind::Coil => {
-                let state = false; // TODO: use simulator's state
-                let draw_iter =
-                    symbol::coil::draw(state).map(|draw| draw.transform(rot_mirror, position));
-                self.draw_symbol(col, draw_iter);
-            }
+        let mut col = self.theme.component;
+        col.w *= 0.5;
+        let draw_iter = symbol
+            .def()
+            .draw(&|_| None)
+            .into_iter()
+            .map(|draw| draw.transform(rot_mirror, position));
+        self.draw_symbol(col, draw_iter);
+    }
+
+    fn draw_dragging_wire(&mut self, wire: Line<[i32; 2]>, grab: Vector2<i32>) {
+        let offset = drag_offset(wire, grab, self.cursor) as f32;
+        let d = if schematic::Horizontal::is_para(wire) {
+            Vector2::new(0., offset)
+        } else {
+            Vector2::new(offset, 0.)
+        };
+        let from: Vector2<f32> = nalgebra::convert(Vector2::from(wire.from));
+        let to: Vector2<f32> = nalgebra::convert(Vector2::from(wire.to));
+        let mut col = self.theme.wire;
+        col.w *= 0.5;
+        let thickness = self.stroke(6.);
+        if self.rubber_band {
+            self.draw_list.add_line(from, from + d, col, thickness);
+            self.draw_list.add_line(to, to + d, col, thickness);
+        }
+        self.draw_list.add_line(from + d, to + d, col, thickness);
+    }
+
+    /// Highlights the segment that `d` or `m` would act on.
+    fn draw_hovered_wire(&mut self) {
+        let pointer: [i32; 2] = self.pointer.into();
+        let over_component = self
+            .sch_state
+            .components_iter(rstar::AABB::from_point(pointer))
+            .next()
+            .is_some();
+        if over_component {
+            return;
+        }
+        let wire = match self.sch_state.wire_at_point(pointer, self.grid_size as i32 / 4) {
+            Some(wire) => wire,
+            None => return,
+        };
+        let from: Vector2<f32> = nalgebra::convert(Vector2::from(wire.from));
+        let to: Vector2<f32> = nalgebra::convert(Vector2::from(wire.to));
+        let col = self.theme.hover;
+        // wider than the wire so the wire shows through
+        self.draw_list.add_line(from, to, col, self.stroke(6.) * (14. / 6.));
+    }
+
+    fn draw_moving_component(&mut self, moving: &MovingComponent) {
+        let (top_left, bottom_right) = self.transform.viewbox();
+        let col = self.theme.guide;
+        let thickness = 1. / self.transform.scale;
+        if let Some(x) = moving.guides.0 {
+            let x = x as f32;
+            self.draw_list.add_line(
+                Vector2::new(x, top_left.y),
+                Vector2::new(x, bottom_right.y),
+                col,
+                thickness,
+            );
+        }
+        if let Some(y) = moving.guides.1 {
+            let y = y as f32;
+            self.draw_list.add_line(
+                Vector2::new(top_left.x, y),
+                Vector2::new(bottom_right.x, y),
+                col,
+                thickness,
+            );
+        }
+        let component = &moving.component;
+        let mut col = self.theme.component;
+        col.w *= 0.5;
+        let draw_iter = component
+            .symbol
+            .def()
+            .draw(&|_| None)
+            .into_iter()
+            .map(|draw| draw.transform(component.rot_mirror, moving.position));
+        self.draw_symbol(col, draw_iter);
+    }
+
+    fn text_halo(&mut self, p: Vector2<f32>, text: &str) {
+        if text.is_empty() {
+            return;
         }
+        let width = FONT.measure(text, self.text_height);
+        let margin = self.text_height * 0.1;
+        let min = Vector2::new(p.x - margin, p.y - self.text_height - margin);
+        let max = Vector2::new(p.x + width + margin, p.y + margin * 2.);
+        let mut col = self.draw_list.bg_color;
+        col.w = 0.8;
+        self.draw_list.add_filled_rect(min, max, margin * 2., col);
     }
 
     fn text(&mut self, p: Vector2<f32>, text: &str) {
+        self.text_with_height(p, text, self.text_height, 3.0);
+    }
+
+    fn text_with_height(&mut self, p: Vector2<f32>, text: &str, text_height: f32, thickness: f32) {
+        let scale = FONT.scale(text_height);
         let mut advance = Vector2::new(0.0f32, 0.0);
         for char in text.chars() {
             if let Some(glyph) = FONT.glyph(char) {
                 for (p1, p2) in glyph {
-                    let p1 = p + (advance + p1).scale(4.5454);
-                    let p2 = p + (advance + p2).scale(4.5454);
-                    let col = Color::new(0., 0., 0., 1.);
-                    self.draw_list.add_line(p1, p2, col, 3.0);
+                    let p1 = p + (advance + p1).scale(scale);
+                    let p2 = p + (advance + p2).scale(scale);
+                    self.draw_list.add_line(p1, p2, self.theme.text, thickness);
                 }
             }
             advance += Vector2::new(FONT.advance(), 0.0);
         }
     }
 
+    /// Waveforms of the probes along the bottom of the screen, in screen space.
+    fn draw_probes(&mut self) {
+        const ROW_HEIGHT: f32 = 24.;
+        const MARGIN: f32 = 8.;
+        const NAME_WIDTH: f32 = 80.;
+        let screen_size: Vector2<f32> = nalgebra::convert(self.transform.screen_size);
+        let top = screen_size.y - MARGIN - ROW_HEIGHT * self.probes.len() as f32;
+        let step = (screen_size.x - NAME_WIDTH - MARGIN * 2.) / probe::HISTORY as f32;
+        let px = 1. / self.transform.scale;
+        let translate = self.transform.offset();
+        let to_world = |x: f32, y: f32| (Vector2::new(x, y) - translate).scale(px);
+        let min = to_world(0., top - MARGIN);
+        let max = to_world(screen_size.x, screen_size.y);
+        let mut bg = self.draw_list.bg_color;
+        bg.w = 0.9;
+        self.draw_list.add_filled_rect(min, max, 0., bg);
+        let col = self.theme.wire;
+        let rows = self
+            .probes
+            .iter()
+            .map(|probe| (probe.name().to_string(), probe.samples().collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        for (row, (name, samples)) in rows.iter().enumerate() {
+            let row_top = top + ROW_HEIGHT * row as f32;
+            let high = row_top + 4.;
+            let low = row_top + ROW_HEIGHT - 4.;
+            self.text_with_height(to_world(MARGIN, low), name, 12. * px, 1.5 * px);
+            let mut prev_y = None;
+            for (i, sample) in samples.iter().enumerate() {
+                let y = match sample {
+                    Some(true) => high,
+                    Some(false) => low,
+                    None => {
+                        prev_y = None;
+                        continue;
+                    }
+                };
+                let x = MARGIN + NAME_WIDTH + step * i as f32;
+                if let Some(prev_y) = prev_y {
+                    if prev_y != y {
+                        self.draw_list.add_line(to_world(x, prev_y), to_world(x, y), col, 2. * px);
+                    }
+                }
+                self.draw_list.add_line(to_world(x, y), to_world(x + step, y), col, 2. * px);
+                prev_y = Some(y);
+            }
+        }
+    }
+
+    /// Runs one simulation pass and samples everything that watches it.
+    fn step_simulation(&mut self) {
+        let circuit = match &mut self.circuit {
+            Some(circuit) => circuit,
+            None => return,
+        };
+        circuit.simulate();
+        self.sim_tick += 1;
+        if let Some(sim_monitor) = &mut self.sim_monitor {
+            sim_monitor.observe(circuit);
+        }
+        for probe in &mut self.probes {
+            probe.sample(circuit);
+        }
+    }
+
     pub fn draw(&mut self) {
-        if let Some(circuit) = &mut self.circuit {
-            circuit.simulate();
+        self.step_simulation();
+        if !self.has_area {
+            return;
         }
         self.draw_grid();
         self.draw_schematic();
+        if !self.selection.is_empty() {
+            self.draw_list.new_layer();
+            self.draw_selection();
+        }
         let state = std::mem::replace(&mut self.tool_state, ToolState::Selection);
         match &state {
             ToolState::Wiring(wiring) => {
@@ -590,46 +1676,1213 @@ impl Cad {
             ToolState::ReadyToWire => {
                 self.draw_cursor();
             }
-            ToolState::Selection => {}
+            ToolState::Selection => {
+                self.draw_list.new_layer();
+                self.draw_hovered_wire();
+            }
             ToolState::PlacingComponent(symbol, rot_mirror) => {
                 self.draw_list.new_layer();
                 self.draw_placing_component(*symbol, *rot_mirror);
                 self.draw_cursor();
             }
+            ToolState::DraggingWire(wire, grab) => {
+                self.draw_list.new_layer();
+                self.draw_dragging_wire(*wire, *grab);
+                self.draw_cursor();
+            }
+            ToolState::MovingComponent(moving) => {
+                self.draw_list.new_layer();
+                self.draw_moving_component(moving);
+            }
         }
         self.tool_state = state;
+        if !self.probes.is_empty() && self.circuit.is_some() {
+            self.draw_list.new_layer();
+            self.draw_probes();
+        }
         self.backend.draw(&self.draw_list).unwrap();
     }
 
+    /// Registers a symbol described by JSON. See `symbol::custom` for the format. Fails if the
+    /// id is already registered, so a symbol can't be redefined once placed.
+    pub fn register_symbol(&mut self, json: &str) -> Result<(), JsValue> {
+        let desc: symbol::custom::Description =
+            serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let def =
+            symbol::custom::Custom::from_description(desc).map_err(|e| JsValue::from_str(&e))?;
+        symbol::register(Box::new(def)).map_err(|e| JsValue::from_str(&e))?;
+        Ok(())
+    }
+
+    /// Starts placing the symbol registered as `id`.
+    pub fn place_symbol(&mut self, id: &str) -> Result<(), JsValue> {
+        if self.read_only {
+            return Err(JsValue::from_str("read-only"));
+        }
+        let kind = symbol::Kind::from_id(id)
+            .ok_or_else(|| JsValue::from_str(&format!("unknown symbol: {}", id)))?;
+        self.tool_state = ToolState::PlacingComponent(kind, Default::default());
+        Ok(())
+    }
+
+    /// Adds components given as a JSON array in the save format. A component that overlaps
+    /// another is moved to the nearest free spot; see `last_import_report` for those and for
+    /// the ones that could not be placed at all. Returns the number placed.
+    pub fn import_components(&mut self, json: &str) -> Result<usize, JsValue> {
+        if self.read_only {
+            return Err(JsValue::from_str("read-only"));
+        }
+        let components: Vec<schematic::Component> =
+            serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(self.import(components, IMPORT_SEARCH_STEPS))
+    }
+
+    /// Changes the component with `id` into the symbol registered as `kind`, keeping its
+    /// position, orientation and label. Returns `false` if it would overlap another component.
+    pub fn replace_component(&mut self, id: u32, kind: &str) -> Result<bool, JsValue> {
+        if self.read_only {
+            return Err(JsValue::from_str("read-only"));
+        }
+        let kind = symbol::Kind::from_id(kind)
+            .ok_or_else(|| JsValue::from_str(&format!("unknown symbol: {}", kind)))?;
+        Ok(self.sch_state.replace_component_kind(id, kind))
+    }
+
+    /// Sets labels from `[{ x, y, label }]`, matching components by position. Entries without
+    /// a component there are skipped. Returns the number of labels applied. Fails while
+    /// simulating, as the labels name the simulator states.
+    pub fn apply_labels(&mut self, json: &str) -> Result<u32, JsValue> {
+        if self.read_only {
+            return Err(JsValue::from_str("read-only"));
+        }
+        #[derive(Deserialize)]
+        struct Entry {
+            x: i32,
+            y: i32,
+            label: String,
+        }
+        let entries: Vec<Entry> =
+            serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let labels = entries
+            .into_iter()
+            .map(|entry| (Vector2::new(entry.x, entry.y), entry.label))
+            .collect();
+        self.relabel(labels)
+            .ok_or_else(|| JsValue::from_str("cannot relabel while simulating"))
+    }
+
+    /// Deletes the component with `id`. Returns `false` if there is none or the schematic is
+    /// read-only.
+    pub fn delete_component(&mut self, id: u32) -> bool {
+        if self.read_only {
+            return false;
+        }
+        self.selection.retain(|&selected| selected != id);
+        self.sch_state.delete_component_by_id(id)
+    }
+
+    /// Selects components by id, replacing the current selection.
+    pub fn set_selection(&mut self, ids: Vec<u32>) {
+        self.selection = ids;
+    }
+
+    pub fn selection(&self) -> Vec<u32> {
+        self.selection.clone()
+    }
+
+    /// The selected components and the wires among them as a JSON snippet for
+    /// `import_snippet`.
+    pub fn export_selection(&self) -> String {
+        let snippet = self.sch_state.extract(&self.selection, self.grid_size as i32);
+        serde_json::to_string(&snippet).unwrap()
+    }
+
+    /// Adds a snippet from `export_selection` with its top-left at the grid point nearest to
+    /// `(x, y)` in world units, and selects it. Clashing labels are renumbered.
+    pub fn import_snippet(&mut self, json: &str, x: f32, y: f32) -> Result<(), JsValue> {
+        if self.read_only {
+            return Err(JsValue::from_str("read-only"));
+        }
+        let snippet: schematic::Snippet =
+            serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let grid_size = self.grid_size as f32;
+        let at = Vector2::new(x, y).map(|f| ((f / grid_size).round() * grid_size) as i32);
+        let ids = self
+            .sch_state
+            .paste(snippet, at)
+            .ok_or_else(|| JsValue::from_str("snippet overlaps existing components"))?;
+        self.selection = ids;
+        Ok(())
+    }
+
+    /// Aligns the selected components on their average `"x"` (a column) or `"y"` (a row),
+    /// snapped to the grid. Returns `false` if nothing moved because they would overlap.
+    pub fn align_selection(&mut self, axis: &str) -> Result<bool, JsValue> {
+        if self.read_only {
+            return Err(JsValue::from_str("read-only"));
+        }
+        let axis = match axis {
+            "x" => schematic::Axis::X,
+            "y" => schematic::Axis::Y,
+            _ => return Err(JsValue::from_str(&format!("unknown axis: {}", axis))),
+        };
+        let selection = &self.selection;
+        let positions = self
+            .sch_state
+            .components()
+            .filter(|c| selection.contains(&c.id))
+            .map(|c| match axis {
+                schematic::Axis::X => c.position.x,
+                schematic::Axis::Y => c.position.y,
+            })
+            .collect::<Vec<_>>();
+        if positions.is_empty() {
+            return Ok(false);
+        }
+        let average = positions.iter().sum::<i32>() as f32 / positions.len() as f32;
+        let grid_size = self.grid_size as f32;
+        let target = ((average / grid_size).round() * grid_size) as i32;
+        Ok(self.sch_state.align_selection(&self.selection, axis, target))
+    }
+
+    /// Draws the wires of `net` (an id from `list_nets`) in a highlight color.
+    pub fn highlight_net(&mut self, net: u32) {
+        self.highlighted_net = Some(net);
+    }
+
+    pub fn clear_net_highlight(&mut self) {
+        self.highlighted_net = None;
+    }
+
+    /// Flips the switch with the given component id in the running simulation. Returns
+    /// `false` when not simulating or the component isn't a switch.
+    pub fn toggle_contact(&mut self, id: u32) -> bool {
+        let circuit = match &mut self.circuit {
+            Some(circuit) => circuit,
+            None => return false,
+        };
+        let component = match self.sch_state.components().find(|c| c.id == id) {
+            Some(component) => component,
+            None => return false,
+        };
+        let toggled = component.symbol.def().toggle(&component.label, circuit);
+        if toggled {
+            if let Some(sim_monitor) = &mut self.sim_monitor {
+                sim_monitor.reset();
+            }
+        }
+        toggled
+    }
+
+    /// Name of the active tool for the host UI: `"selection"`, `"ready_to_wire"`, `"wiring"`,
+    /// `"placing:<symbol id in lower case>"`, `"dragging_wire"` or `"moving_component"`.
+    pub fn active_tool(&self) -> String {
+        match &self.tool_state {
+            ToolState::Selection => "selection".to_string(),
+            ToolState::ReadyToWire => "ready_to_wire".to_string(),
+            ToolState::Wiring(_) => "wiring".to_string(),
+            ToolState::PlacingComponent(symbol, _) => {
+                format!("placing:{}", symbol.id().to_lowercase())
+            }
+            ToolState::DraggingWire(_, _) => "dragging_wire".to_string(),
+            ToolState::MovingComponent(_) => "moving_component".to_string(),
+        }
+    }
+
+    /// The netlist as Graphviz DOT, for looking at the logic structure.
+    pub fn export_graph_dot(&self) -> String {
+        self.sch_state.graph_dot()
+    }
+
+    /// The schematic as an SVG document in the current theme. Components keep their symbol,
+    /// label, position and orientation in `data-` attributes.
+    pub fn export_svg(&self) -> String {
+        svg::export(&self.sch_state, &self.theme, self.text_height)
+    }
+
+    /// Switches to `tool` as named by `active_tool`: `"selection"`, `"ready_to_wire"`,
+    /// `"wiring"`, or `"placing:<symbol>"`. The symbol of `"placing"` may also be given as
+    /// `kind`.
+    pub fn set_tool(&mut self, tool: &str, kind: Option<String>) -> Result<(), JsValue> {
+        let (tool, suffix) = match tool.split_once(':') {
+            Some((tool, suffix)) => (tool, Some(suffix.to_string())),
+            None => (tool, None),
+        };
+        let state = match (tool, suffix.or(kind)) {
+            ("selection", None) => ToolState::Selection,
+            _ if self.read_only => return Err(JsValue::from_str("read-only")),
+            ("ready_to_wire", None) => ToolState::ReadyToWire,
+            ("wiring", None) => ToolState::Wiring(Wiring::start(self.wiring_start())),
+            ("placing", Some(kind)) => {
+                let kind = symbol::Kind::from_id_ignore_case(&kind)
+                    .ok_or_else(|| JsValue::from_str(&format!("unknown symbol: {}", kind)))?;
+                ToolState::PlacingComponent(kind, Default::default())
+            }
+            (tool, _) => return Err(JsValue::from_str(&format!("cannot set tool: {}", tool))),
+        };
+        self.tool_state = state;
+        Ok(())
+    }
+
     pub fn save_schematic(&self) -> String {
         serde_json::to_string(&self.sch_state).unwrap()
     }
-    pub fn load_schematic(&mut self, json: String) {
-        self.sch_state = serde_json::from_str(&json).unwrap();
+    /// Replaces the schematic. Fails, keeping the current one, if `json` is malformed or uses a
+    /// symbol that isn't registered.
+    pub fn load_schematic(&mut self, json: String) -> Result<(), JsValue> {
+        let sch_state =
+            serde_json::from_str(&json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.replace_schematic(sch_state);
+        Ok(())
     }
     pub fn start_simulation(&mut self) {
         let netlist = self.sch_state.build_netlist();
         let spec = zuse_core::compile(&netlist);
         let mut circuit = spec.build();
+        let mut sim_monitor = SimMonitor::new(&netlist);
         circuit.simulate();
+        sim_monitor.observe(&circuit);
         self.circuit = Some(circuit);
+        self.sim_monitor = Some(sim_monitor);
+        // the pass above counts
+        self.sim_tick = 1;
+        for probe in &mut self.probes {
+            probe.clear();
+        }
     }
     pub fn stop_simulation(&mut self) {
         self.circuit = None;
+        self.sim_monitor = None;
+    }
+
+    pub fn toggle_simulation(&mut self) {
+        if self.circuit.is_some() {
+            self.stop_simulation();
+        } else {
+            self.start_simulation();
+        }
+    }
+
+    /// Simulation passes run since the simulation started, for animations.
+    pub fn sim_tick(&self) -> u64 {
+        self.sim_tick
+    }
+
+    /// `"running"` or `"stopped"`.
+    pub fn simulation_status(&self) -> String {
+        if self.circuit.is_some() {
+            "running".to_string()
+        } else {
+            "stopped".to_string()
+        }
+    }
+
+    /// Shows the waveform of a state (e.g. `R1.A`, or just `R1` for its `.A` state) at the
+    /// bottom of the screen while simulating. Nets have no state of their own in the
+    /// simulator, so they can't be probed.
+    pub fn add_probe(&mut self, state: String) {
+        self.probes.push(Probe::new(state));
+    }
+
+    pub fn clear_probes(&mut self) {
+        self.probes.clear();
     }
 }
 
 #[wasm_bindgen]
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Serialize)]
 pub struct ComponentMetadata {
+    id: u32,
     position: (i32, i32),
     label: String,
+    value: String,
     symbol: symbol::Kind,
+    /// Same encoding as in saved schematics, so a layout can be rebuilt from it.
+    rot_mirror: schematic::RotMirror,
+}
+
+impl From<&schematic::Component> for ComponentMetadata {
+    fn from(component: &schematic::Component) -> Self {
+        Self {
+            id: component.id,
+            position: (component.position.x, component.position.y),
+            label: component.label.clone(),
+            value: component.value.clone(),
+            symbol: component.symbol,
+            rot_mirror: component.rot_mirror,
+        }
+    }
 }
 
 #[wasm_bindgen]
 impl ComponentMetadata {
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
     pub fn label(&self) -> String {
         self.label.clone()
     }
+
+    pub fn value(&self) -> String {
+        self.value.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::RecordingBackend;
+
+    fn cad() -> (Cad, RecordingBackend) {
+        let backend = RecordingBackend::new();
+        let cad = Cad::with_backend(Box::new(backend.clone()));
+        (cad, backend)
+    }
+
+    fn io() -> Io {
+        let mut io = Io::new();
+        io.set_screen_size(800, 600, 1.);
+        io
+    }
+
+    /// Runs one frame with the events queued in `io` and returns what was drawn.
+    fn frame(cad: &mut Cad, backend: &RecordingBackend, io: &mut Io) -> DrawList {
+        cad.new_frame(io);
+        cad.draw();
+        backend.last().unwrap()
+    }
+
+    /// The color of the first vertex of each non-empty command, in drawing order.
+    fn layer_colors(list: &DrawList) -> Vec<Color> {
+        let (vertices, indices) = (list.vertices(), list.indices());
+        list.cmds
+            .iter()
+            .filter(|cmd| cmd.num_of_elems > 0)
+            .map(|cmd| {
+                let vtx = (cmd.vtx_offset + indices[cmd.idx_offset] as usize) * 6;
+                let col = &vertices[vtx + 2..vtx + 6];
+                Color::new(col[0], col[1], col[2], col[3])
+            })
+            .collect()
+    }
+
+    /// Vertex positions of `text` drawn at the origin with hairline strokes.
+    fn text_points(cad: &mut Cad, text: &str, text_height: f32) -> Vec<Vector2<f32>> {
+        cad.draw_list.clear();
+        cad.text_with_height(Vector2::zeros(), text, text_height, 0.);
+        let vertices = cad.draw_list.vertices();
+        vertices.chunks(6).map(|v| Vector2::new(v[0], v[1])).collect()
+    }
+
+    #[test]
+    fn schematic_is_drawn_in_layers() {
+        let (mut cad, backend) = cad();
+        cad.sch_state.add_wire(schematic::WireH { y: 100, x1: 100, x2: 300 });
+        cad.sch_state.add_wire(schematic::WireV { x: 200, y1: 100, y2: 200 });
+        cad.sch_state.add_component(schematic::Component::new(
+            Vector2::new(500, 200),
+            symbol::Kind::CONTACT,
+            schematic::RotMirror::default(),
+            "R1".to_owned(),
+        ));
+        let list = frame(&mut cad, &backend, &mut io());
+
+        let theme = Theme::default();
+        let mut halo = theme.background;
+        halo.w = 0.8;
+        let colors = layer_colors(&list);
+        // grid, wires, junctions, components, label halos, labels
+        assert_eq!(colors.len(), 6);
+        assert_eq!(colors[1..], [theme.wire, theme.wire, theme.component, halo, theme.text]);
+        let cmds = list.cmds.iter().filter(|cmd| cmd.num_of_elems > 0).collect::<Vec<_>>();
+        assert!(cmds.windows(2).all(|w| w[0].idx_offset < w[1].idx_offset));
+    }
+
+    #[test]
+    fn text_scales_glyphs_and_advance_together() {
+        let (mut cad, _) = cad();
+        let small = text_points(&mut cad, "AA", 50.);
+        let large = text_points(&mut cad, "AA", 100.);
+        assert_eq!(small.len(), large.len());
+        for (s, l) in small.iter().zip(&large) {
+            assert!((s.scale(2.) - l).norm() < 1e-3);
+        }
+
+        // the second glyph starts one measured character after the first
+        let right = |points: &[Vector2<f32>]| points.iter().map(|p| p.x).fold(f32::MIN, f32::max);
+        for text_height in [50., 100.] {
+            let one = text_points(&mut cad, "A", text_height);
+            let two = text_points(&mut cad, "AA", text_height);
+            let advance = right(&two) - right(&one);
+            assert!((advance - FONT.measure("A", text_height)).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn label_halo_spans_measured_text() {
+        let (mut cad, _) = cad();
+        cad.draw_list.clear();
+        cad.text_halo(Vector2::new(100., 200.), "R12");
+        let vertices = cad.draw_list.vertices();
+        assert!(!vertices.is_empty());
+        let xs = vertices.chunks(6).map(|v| v[0]);
+        let (left, right) = xs.fold((f32::MAX, f32::MIN), |(l, r), x| (l.min(x), r.max(x)));
+        let margin = cad.text_height * 0.1;
+        assert!((left - (100. - margin)).abs() < 1e-3);
+        assert!((right - (100. + FONT.measure("R12", cad.text_height) + margin)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn wiring_skips_zero_length_segments() {
+        let mut wiring = Wiring::start(Vector2::new(100, 100));
+        wiring.add_segment(Vector2::new(100, 100));
+        assert!(wiring.segments.is_empty());
+        assert_eq!(wiring.last, Vector2::new(100, 100));
+
+        wiring.add_segment(Vector2::new(300, 100));
+        wiring.add_segment(Vector2::new(300, 100));
+        assert_eq!(wiring.segments.len(), 1);
+
+        // clicking twice on the same point and finishing commits nothing
+        let (mut cad, backend) = cad();
+        cad.tool_state = ToolState::Wiring(Wiring::start(cad.cursor));
+        let mut io = io();
+        io.push_click(0);
+        io.push_click(0);
+        io.push_double_click(0);
+        frame(&mut cad, &backend, &mut io);
+        assert!(matches!(cad.tool_state, ToolState::ReadyToWire));
+        assert_eq!(cad.sch_state.wire_count(), 0);
+        let everywhere = rstar::AABB::from_corners([-1000, -1000], [1000, 1000]);
+        assert_eq!(cad.sch_state.junctions_iter(&everywhere).count(), 0);
+    }
+
+    /// The change of scale after one frame pinching by `pinch`.
+    fn pinch_zoom(cad: &mut Cad, pinch: f32) -> f32 {
+        let before = cad.transform.scale;
+        let mut io = io();
+        io.set_pinch(pinch);
+        cad.new_frame(&mut io);
+        cad.transform.scale - before
+    }
+
+    #[test]
+    fn zoom_sensitivity_scales_pinch() {
+        let (mut normal, _) = cad();
+        let (mut sensitive, _) = cad();
+        sensitive.set_zoom_sensitivity(2.);
+        let normal = pinch_zoom(&mut normal, -5.);
+        let doubled = pinch_zoom(&mut sensitive, -5.);
+        assert!(normal > 0.);
+        assert!((doubled - normal * 2.).abs() < 1e-5);
+    }
+
+    /// The change of translation after one frame scrolling the wheel by `wheel_y`.
+    fn wheel_pan(cad: &mut Cad, wheel_y: f32) -> f32 {
+        let before = cad.transform.translate.y;
+        let mut io = io();
+        io.set_wheel_y(wheel_y);
+        cad.new_frame(&mut io);
+        cad.transform.translate.y - before
+    }
+
+    #[test]
+    fn invert_flags_flip_pan_and_zoom() {
+        let (mut normal, _) = cad();
+        let (mut inverted, _) = cad();
+        inverted.set_invert_wheel(true);
+        inverted.set_invert_zoom(true);
+
+        let pan = wheel_pan(&mut normal, 10.);
+        assert!(pan != 0.);
+        assert_eq!(wheel_pan(&mut inverted, 10.), -pan);
+
+        let zoom = pinch_zoom(&mut normal, -5.);
+        assert!(zoom > 0.);
+        assert!(pinch_zoom(&mut inverted, -5.) < 0.);
+    }
+
+    #[test]
+    fn pan_by_moves_the_view_in_screen_pixels() {
+        let (mut zoomed, _) = cad();
+        zoomed.transform.scale = 2.;
+        zoomed.pan_by(30., -40.);
+        assert_eq!(zoomed.transform.translate, Vector2::new(30., -40.));
+        assert_eq!(zoomed.transform.screen_to_world(Vector2::new(30., -40.)), Vector2::zeros());
+
+        // arrow keys pan by a fixed step, but not over a component
+        let (mut cad, backend) = cad();
+        let mut io = io();
+        io.push_keydown("ArrowLeft".into());
+        frame(&mut cad, &backend, &mut io);
+        assert_eq!(cad.transform.translate, Vector2::new(KEY_PAN_STEP, 0.));
+        cad.sch_state.add_component(schematic::Component::new(
+            Vector2::new(-50, 0),
+            symbol::Kind::CONTACT,
+            schematic::RotMirror::default(),
+            "R1".to_owned(),
+        ));
+        io.push_keydown("ArrowLeft".into());
+        frame(&mut cad, &backend, &mut io);
+        assert_eq!(cad.transform.translate, Vector2::new(KEY_PAN_STEP, 0.));
+    }
+
+    #[test]
+    fn components_in_rect_lists_intersecting_components() {
+        let (mut cad, _) = cad();
+        for (x, label) in [(0, "R1"), (200, "R2"), (1000, "R3")] {
+            cad.sch_state.add_component(schematic::Component::new(
+                Vector2::new(x, 0),
+                symbol::Kind::CONTACT,
+                schematic::RotMirror::default(),
+                label.to_owned(),
+            ));
+        }
+        let mut labels = cad
+            .components_in([300, 50], [-100, -50])
+            .into_iter()
+            .map(|component| component.label)
+            .collect::<Vec<_>>();
+        labels.sort();
+        assert_eq!(labels, ["R1", "R2"]);
+        assert!(cad.components_in([400, 400], [600, 600]).is_empty());
+    }
+
+    fn add(cad: &mut Cad, symbol: symbol::Kind, x: i32, y: i32, label: &str) -> u32 {
+        let component = schematic::Component::new(
+            Vector2::new(x, y),
+            symbol,
+            schematic::RotMirror::default(),
+            label.to_owned(),
+        );
+        cad.sch_state.add_component(component).unwrap()
+    }
+
+    #[test]
+    fn toggle_contact_flips_its_states_by_id() {
+        let (mut cad, _) = cad();
+        let contact = add(&mut cad, symbol::Kind::CONTACT, 0, 200, "R1");
+        let power = add(&mut cad, symbol::Kind::POWER, 0, 0, "V");
+        assert!(!cad.toggle_contact(contact));
+
+        cad.start_simulation();
+        let state = |cad: &Cad, name: &str| cad.circuit.as_ref().unwrap().get_state(name);
+        assert!(cad.toggle_contact(contact));
+        assert_eq!(state(&cad, "R1.A"), Some(true));
+        assert_eq!(state(&cad, "R1.B"), Some(false));
+        assert!(cad.toggle_contact(contact));
+        assert_eq!(state(&cad, "R1.A"), Some(false));
+        assert_eq!(state(&cad, "R1.B"), Some(true));
+
+        assert!(!cad.toggle_contact(power));
+        assert!(!cad.toggle_contact(contact + power + 1));
+    }
+
+    #[test]
+    fn wire_starts_by_key_or_click() {
+        let starts = [
+            (ToolState::Selection, io::Event::Keydown("w".into())),
+            (ToolState::ReadyToWire, io::Event::Keydown("w".into())),
+            (ToolState::ReadyToWire, io::Event::Click(0)),
+        ];
+        for (tool_state, event) in starts {
+            let (mut cad, backend) = cad();
+            cad.tool_state = tool_state;
+            let mut io = io();
+            io.set_mouse_x(110.);
+            io.set_mouse_y(90.);
+            let name = format!("{:?}", event);
+            io.events.push(event);
+            frame(&mut cad, &backend, &mut io);
+            match &cad.tool_state {
+                ToolState::Wiring(wiring) => {
+                    assert_eq!(wiring.last, Vector2::new(100, 100));
+                    assert!(wiring.segments.is_empty());
+                }
+                _ => panic!("not wiring after {}", name),
+            }
+        }
+
+        // a click in selection toggles contacts instead
+        let (mut cad, backend) = cad();
+        let mut io = io();
+        io.push_click(0);
+        frame(&mut cad, &backend, &mut io);
+        assert!(matches!(cad.tool_state, ToolState::Selection));
+    }
+
+    #[test]
+    fn active_tool_follows_tool_state() {
+        let (mut cad, backend) = cad();
+        assert_eq!(cad.active_tool(), "selection");
+        let mut io = io();
+        io.push_keydown("w".into());
+        frame(&mut cad, &backend, &mut io);
+        assert_eq!(cad.active_tool(), "wiring");
+
+        cad.tool_state = ToolState::Selection;
+        io.push_keydown("c".into());
+        frame(&mut cad, &backend, &mut io);
+        assert_eq!(cad.active_tool(), "placing:coil");
+    }
+
+    #[test]
+    fn right_click_cancels_placing_and_wiring() {
+        let mut wiring = Wiring::start(Vector2::new(0, 0));
+        wiring.add_segment(Vector2::new(200, 100));
+        let modes = [
+            ToolState::PlacingComponent(symbol::Kind::CONTACT, Default::default()),
+            ToolState::Wiring(wiring),
+        ];
+        for tool_state in modes {
+            let (mut cad, backend) = cad();
+            cad.tool_state = tool_state;
+            let mut io = io();
+            io.push_click(2);
+            io.push_double_click(0);
+            frame(&mut cad, &backend, &mut io);
+            assert_eq!(cad.active_tool(), "selection");
+            assert_eq!(cad.sch_state.component_count(), 0);
+            assert_eq!(cad.sch_state.wire_count(), 0);
+        }
+    }
+
+    #[test]
+    fn only_the_value_is_editable_while_simulating() {
+        let (mut cad, _) = cad();
+        add(&mut cad, symbol::Kind::CONTACT, 0, 0, "R1");
+        // answers only when asked for `asked`
+        let answer = |asked: &'static str| {
+            move |message: &str, _: &str| {
+                if message == asked {
+                    Some(format!("{} edited", asked))
+                } else {
+                    None
+                }
+            }
+        };
+
+        cad.edit_component_at_cursor(answer("Label"));
+        cad.start_simulation();
+        cad.edit_component_at_cursor(answer("Label"));
+        cad.edit_component_at_cursor(answer("Value"));
+        let component = cad.sch_state.components().next().unwrap();
+        assert_eq!(component.label, "Label edited");
+        assert_eq!(component.value, "Value edited");
+
+        cad.stop_simulation();
+        cad.edit_component_at_cursor(answer("Value"));
+        let component = cad.sch_state.components().next().unwrap();
+        assert_eq!(component.label, "Label edited");
+    }
+
+    #[test]
+    fn v_and_double_click_edit_the_component_under_the_cursor() {
+        let (mut cad, backend) = cad();
+        add(&mut cad, symbol::Kind::CONTACT, 0, 0, "R1");
+        cad.prompt = |message, _| Some(format!("{} typed", message));
+        let mut io = io();
+        // off the contact, but snapped onto its edge
+        io.set_mouse_x(70.);
+        io.push_keydown("v".into());
+        frame(&mut cad, &backend, &mut io);
+        let component = cad.sch_state.components().next().unwrap();
+        assert_eq!((component.label.as_str(), component.value.as_str()), ("R1", "Value typed"));
+
+        io.push_keyup("v".into());
+        io.push_double_click(0);
+        frame(&mut cad, &backend, &mut io);
+        let component = cad.sch_state.components().next().unwrap();
+        assert_eq!(component.label, "Label typed");
+    }
+
+    /// Width and height of what `draw` adds to a cleared draw list.
+    fn drawn_size(cad: &mut Cad, draw: impl FnOnce(&mut Cad)) -> Vector2<f32> {
+        cad.draw_list.clear();
+        draw(cad);
+        let points = cad.draw_list.vertices().chunks(6).map(|v| Vector2::new(v[0], v[1]));
+        let (min, max) = points.fold(
+            (Vector2::new(f32::MAX, f32::MAX), Vector2::new(f32::MIN, f32::MIN)),
+            |(min, max), p| (min.inf(&p), max.sup(&p)),
+        );
+        max - min
+    }
+
+    fn drawn_radius(cad: &mut Cad, draw: impl FnOnce(&mut Cad)) -> f32 {
+        drawn_size(cad, draw).x * 0.5
+    }
+
+    #[test]
+    fn corner_disc_is_as_wide_as_the_wire() {
+        let (mut cad, _) = cad();
+        cad.set_round_wire_corners(true);
+        let corner = |cad: &mut Cad| cad.junction(Vector2::new(100, 100), 2);
+        assert!((drawn_radius(&mut cad, corner) - 3.).abs() < 1e-4);
+
+        // a 2px screen stroke at 4px per world unit is half a world unit wide
+        cad.set_screen_stroke(Some(2.));
+        cad.transform.scale = 4.;
+        assert!((drawn_radius(&mut cad, corner) - 0.25).abs() < 1e-4);
+    }
+
+    #[test]
+    fn blinker_trace_alternates() {
+        // power into the common of R1, whose resting B throw drives R1's own coil
+        let (mut cad, _) = cad();
+        add(&mut cad, symbol::Kind::POWER, 0, 0, "V");
+        add(&mut cad, symbol::Kind::CONTACT, 0, 200, "R1");
+        add(&mut cad, symbol::Kind::COIL, 50, 500, "R1");
+        cad.sch_state.add_wire(schematic::WireV { x: 0, y1: 0, y2: 100 });
+        cad.sch_state.add_wire(schematic::WireV { x: 50, y1: 300, y2: 400 });
+        assert_eq!(cad.trace(4), None);
+
+        cad.start_simulation();
+        assert_eq!(cad.sim_monitor.as_ref().unwrap().names(), ["R1.A", "R1.B"]);
+        let trace = cad.trace(6).unwrap();
+        assert_eq!(trace.len(), 6);
+        for step in &trace {
+            assert_eq!(step[0].map(|a| !a), step[1]);
+        }
+        assert!(trace.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn space_starts_then_stops_simulation() {
+        let (mut cad, backend) = cad();
+        let mut io = io();
+        io.push_keydown(" ".into());
+        frame(&mut cad, &backend, &mut io);
+        assert_eq!(cad.simulation_status(), "running");
+        assert_eq!(cad.active_tool(), "selection");
+
+        io.push_keyup(" ".into());
+        io.push_keydown(" ".into());
+        frame(&mut cad, &backend, &mut io);
+        assert_eq!(cad.simulation_status(), "stopped");
+    }
+
+    #[test]
+    fn import_reports_moved_and_rejected_components() {
+        let contact = |x, label: &str| {
+            let position = Vector2::new(x, 0);
+            let rot_mirror = schematic::RotMirror::default();
+            schematic::Component::new(position, symbol::Kind::CONTACT, rot_mirror, label.to_owned())
+        };
+        let (mut cad, _) = cad();
+        add(&mut cad, symbol::Kind::CONTACT, 0, 0, "R1");
+
+        let components = vec![contact(1000, "R2"), contact(0, "R3")];
+        assert_eq!(cad.import(components, IMPORT_SEARCH_STEPS), 2);
+        let report = &cad.last_import_report;
+        assert_eq!(report.placed, 2);
+        assert!(report.rejected.is_empty());
+        assert_eq!(report.moved.len(), 1);
+        assert_eq!(report.moved[0].label, "R3");
+        assert_eq!(report.moved[0].position, (0, 0));
+        assert_ne!(report.moved[0].placed_at, Some((0, 0)));
+
+        // with nowhere to move to, the overlapping one is rejected
+        assert_eq!(cad.import(vec![contact(0, "R4")], 0), 0);
+        let report = &cad.last_import_report;
+        assert_eq!(report.placed, 0);
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].reason.as_deref(), Some("overlap"));
+        assert_eq!(cad.sch_state.component_count(), 3);
+    }
+
+    #[test]
+    fn overlay_widths_follow_the_screen_stroke() {
+        let (mut cad, _) = cad();
+        cad.sch_state.add_wire(schematic::WireH { y: 0, x1: 0, x2: 200 });
+        cad.pointer = Vector2::new(100, 0);
+        let dot = |cad: &mut Cad| cad.junction(Vector2::new(0, 0), 3);
+        let wire = Line::new([0, 0], [200, 0]);
+        let dragging = |cad: &mut Cad| cad.draw_dragging_wire(wire, Vector2::zeros());
+        let hovered = |cad: &mut Cad| cad.draw_hovered_wire();
+        let widths = |cad: &mut Cad| {
+            [
+                drawn_radius(cad, dot) * 2.,
+                drawn_size(cad, dragging).y,
+                drawn_size(cad, hovered).y,
+            ]
+        };
+        assert_eq!(widths(&mut cad), [40., 6., 14.]);
+
+        // 3px at 2px per world unit: the wire is 1.5 world units and the rest keep their ratio
+        cad.set_screen_stroke(Some(3.));
+        cad.transform.scale = 2.;
+        cad.draw_list.scale = 2.;
+        let [dot, dragging, hovered] = widths(&mut cad);
+        assert!((dot - 10.).abs() < 1e-4);
+        assert!((dragging - 1.5).abs() < 1e-4);
+        assert!((hovered - 3.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn shift_pans_over_a_component_until_released() {
+        let (mut cad, backend) = cad();
+        let mut io = io();
+        add(&mut cad, symbol::Kind::CONTACT, -50, 0, "R1");
+        let arrow = |cad: &mut Cad, io: &mut io::Io| {
+            io.push_keydown("ArrowLeft".into());
+            frame(cad, &backend, io);
+            cad.transform.translate.x
+        };
+
+        io.push_keydown("Shift".into());
+        assert_eq!(arrow(&mut cad, &mut io), KEY_PAN_STEP);
+        io.push_keyup("Shift".into());
+        assert_eq!(arrow(&mut cad, &mut io), KEY_PAN_STEP);
+
+        // a blur drops modifiers whose keyup went elsewhere
+        io.push_keydown("Shift".into());
+        io.push_blur();
+        assert_eq!(arrow(&mut cad, &mut io), KEY_PAN_STEP);
+        assert_eq!(cad.modifiers, io::Modifiers::default());
+    }
+
+    #[test]
+    fn held_w_adds_one_segment_until_released() {
+        let (mut cad, backend) = cad();
+        cad.tool_state = ToolState::Wiring(Wiring::start(Vector2::new(0, 0)));
+        let mut io = io();
+        let segments = |cad: &Cad| match &cad.tool_state {
+            ToolState::Wiring(wiring) => wiring.segments.len(),
+            _ => panic!("not wiring"),
+        };
+        // the pointer moves between repeats, so each would otherwise add a segment
+        for (x, y) in [(200., 0.), (200., 200.), (0., 200.)] {
+            io.set_mouse_x(x);
+            io.set_mouse_y(y);
+            io.push_keydown("w".into());
+            frame(&mut cad, &backend, &mut io);
+        }
+        assert_eq!(segments(&cad), 1);
+
+        io.push_keyup("w".into());
+        io.push_keydown("w".into());
+        frame(&mut cad, &backend, &mut io);
+        assert_eq!(segments(&cad), 2);
+    }
+
+    #[test]
+    fn remapped_primary_button_places_segments() {
+        let (mut cad, backend) = cad();
+        cad.set_primary_button(2);
+        cad.set_secondary_button(0);
+        cad.tool_state = ToolState::Wiring(Wiring::start(Vector2::new(0, 0)));
+        let mut io = io();
+        io.set_mouse_x(200.);
+        io.push_click(2);
+        frame(&mut cad, &backend, &mut io);
+        match &cad.tool_state {
+            ToolState::Wiring(wiring) => assert_eq!(wiring.segments.len(), 1),
+            _ => panic!("not wiring"),
+        }
+
+        io.push_click(0);
+        frame(&mut cad, &backend, &mut io);
+        assert_eq!(cad.active_tool(), "selection");
+    }
+
+    #[test]
+    fn empty_schematic_still_draws_the_grid() {
+        let (mut cad, backend) = cad();
+        let list = frame(&mut cad, &backend, &mut io());
+        let (top_left, bottom_right) = cad.grid_viewbox();
+        let step = cad.grid_size as i32;
+        let dots = |from: i32, to: i32| (from - from % step..to).step_by(step as usize).count();
+        let expected = dots(top_left.x, bottom_right.x) * dots(top_left.y, bottom_right.y);
+        assert!(expected > 0);
+
+        let cmds = list.cmds.iter().filter(|cmd| cmd.num_of_elems > 0).collect::<Vec<_>>();
+        assert_eq!(cmds.len(), 1);
+        // a square, two triangles, per grid point
+        assert_eq!(cmds[0].num_of_elems, expected * 2);
+    }
+
+    #[test]
+    fn resize_keeps_the_center_world_point() {
+        for center_origin in [false, true] {
+            let (mut cad, backend) = cad();
+            cad.set_center_origin(center_origin);
+            let mut io = io();
+            frame(&mut cad, &backend, &mut io);
+            cad.transform.scale = 2.;
+            cad.pan_by(30., -40.);
+            let center = |cad: &Cad| {
+                let size = nalgebra::convert::<_, Vector2<f32>>(cad.transform.screen_size);
+                cad.transform.screen_to_world(size.scale(0.5))
+            };
+            let before = center(&cad);
+
+            io.set_screen_size(600, 1000, 1.);
+            frame(&mut cad, &backend, &mut io);
+            assert!((center(&cad) - before).norm() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn read_only_ignores_editing_but_navigates() {
+        let (mut cad, backend) = cad();
+        let id = add(&mut cad, symbol::Kind::CONTACT, 500, 500, "R1");
+        cad.set_read_only(true);
+        let mut io = io();
+        io.push_keydown("w".into());
+        io.push_keydown("c".into());
+        frame(&mut cad, &backend, &mut io);
+        assert_eq!(cad.active_tool(), "selection");
+        assert!(!cad.delete_component(id));
+        assert_eq!(cad.sch_state.component_count(), 1);
+
+        io.push_keydown("ArrowLeft".into());
+        frame(&mut cad, &backend, &mut io);
+        assert_eq!(cad.transform.translate.x, KEY_PAN_STEP);
+        assert!(wheel_pan(&mut cad, 10.) != 0.);
+        assert!(pinch_zoom(&mut cad, 5.) != 0.);
+    }
+
+    #[test]
+    fn stepping_advances_the_sim_tick() {
+        let (mut cad, _) = cad();
+        cad.step_simulation();
+        assert_eq!(cad.sim_tick(), 0);
+
+        cad.start_simulation();
+        assert_eq!(cad.sim_tick(), 1);
+        cad.step_simulation();
+        cad.step_simulation();
+        assert_eq!(cad.sim_tick(), 3);
+
+        cad.stop_simulation();
+        cad.start_simulation();
+        assert_eq!(cad.sim_tick(), 1);
+    }
+
+    #[test]
+    fn hovering_a_wire_highlights_it() {
+        let (mut cad, backend) = cad();
+        cad.sch_state.add_wire(schematic::WireH { y: 100, x1: 100, x2: 300 });
+        let mut io = io();
+        io.set_mouse_x(200.);
+        io.set_mouse_y(105.);
+        let list = frame(&mut cad, &backend, &mut io);
+        let hover = cad.theme.hover;
+        assert_eq!(layer_colors(&list).last(), Some(&hover));
+
+        let points = list
+            .vertices()
+            .chunks(6)
+            .filter(|v| Color::new(v[2], v[3], v[4], v[5]) == hover)
+            .map(|v| Vector2::new(v[0], v[1]))
+            .collect::<Vec<_>>();
+        let (min, max) = points.iter().fold(
+            (Vector2::new(f32::MAX, f32::MAX), Vector2::new(f32::MIN, f32::MIN)),
+            |(min, max), p| (min.inf(p), max.sup(p)),
+        );
+        let half_width = 7.;
+        assert!((min.x - 100.).abs() <= half_width && (max.x - 300.).abs() <= half_width);
+        assert!((min.y - (100. - half_width)).abs() < 1e-3);
+        assert!((max.y - (100. + half_width)).abs() < 1e-3);
+
+        // nothing to highlight away from the wire
+        io.set_mouse_y(300.);
+        let list = frame(&mut cad, &backend, &mut io);
+        assert!(!layer_colors(&list).contains(&hover));
+    }
+
+    #[test]
+    fn selection_overlay_uses_the_theme() {
+        let (mut cad, backend) = cad();
+        let id = add(&mut cad, symbol::Kind::CONTACT, 200, 200, "R1");
+        cad.set_selection(vec![id]);
+        let selection = Color::new(1., 0., 1., 1.);
+        cad.theme = Theme {
+            selection,
+            ..Theme::default()
+        };
+        let list = frame(&mut cad, &backend, &mut io());
+        assert!(layer_colors(&list).contains(&selection));
+        assert!(!layer_colors(&list).contains(&Theme::default().selection));
+    }
+
+    #[test]
+    fn hop_bridges_a_crossover_and_dot_does_not() {
+        // wire-colored points near the crossing but off both wires, which only a bridge has
+        let off_the_wires = |style: &str| {
+            let (mut cad, backend) = cad();
+            cad.set_crossing_style(style).unwrap();
+            cad.sch_state.add_wire(schematic::WireH { y: 100, x1: 0, x2: 200 });
+            cad.sch_state.add_wire(schematic::WireV { x: 100, y1: 0, y2: 200 });
+            let list = frame(&mut cad, &backend, &mut io());
+            let wire = cad.theme.wire;
+            list.vertices()
+                .chunks(6)
+                .filter(|v| Color::new(v[2], v[3], v[4], v[5]) == wire)
+                .map(|v| ((v[0] - 100.).abs(), (v[1] - 100.).abs()))
+                .filter(|&(dx, dy)| dx > 4. && dy > 4. && dx.max(dy) < 30.)
+                .count()
+        };
+        assert_eq!(off_the_wires("dot"), 0);
+        assert!(off_the_wires("hop") > 0);
+    }
+
+    #[test]
+    fn wiring_starts_at_a_nearby_junction() {
+        let (mut cad, backend) = cad();
+        // ends off the grid, as after a drag
+        cad.sch_state.add_wire(schematic::WireH { y: 105, x1: 10, x2: 110 });
+        let mut io = io();
+        io.set_mouse_x(118.);
+        io.set_mouse_y(100.);
+        io.push_keydown("w".into());
+        frame(&mut cad, &backend, &mut io);
+        io.set_mouse_x(300.);
+        io.set_mouse_y(105.);
+        io.push_click(0);
+        io.push_double_click(0);
+        frame(&mut cad, &backend, &mut io);
+
+        assert_eq!(cad.sch_state.wire_count(), 1);
+        let mut junctions = cad
+            .sch_state
+            .junctions_iter(&rstar::AABB::from_corners([0, 0], [400, 400]))
+            .map(|(p, count)| (<[i32; 2]>::from(p), count))
+            .collect::<Vec<_>>();
+        junctions.sort_unstable();
+        // one wire on from the existing one, not a second one from the cursor
+        assert_eq!(junctions, vec![([10, 105], 1), ([300, 105], 1)]);
+    }
+
+    #[test]
+    fn set_tool_round_trips_with_active_tool() {
+        let (mut cad, _) = cad();
+        cad.set_tool("placing:coil", None).unwrap();
+        assert_eq!(cad.active_tool(), "placing:coil");
+        assert!(matches!(
+            cad.tool_state,
+            ToolState::PlacingComponent(symbol::Kind::COIL, _)
+        ));
+
+        cad.set_tool("placing", Some("Contact".to_owned())).unwrap();
+        assert_eq!(cad.active_tool(), "placing:contact");
+        for tool in ["wiring", "ready_to_wire", "selection"] {
+            cad.set_tool(tool, None).unwrap();
+            assert_eq!(cad.active_tool(), tool);
+        }
+    }
+
+    #[test]
+    fn labels_apply_by_position_unless_simulating() {
+        let (mut cad, _) = cad();
+        let contact = add(&mut cad, symbol::Kind::CONTACT, 0, 0, "R");
+        let coil = add(&mut cad, symbol::Kind::COIL, 200, 0, "R");
+        let labels = |cad: &Cad| {
+            let label = |id| cad.sch_state.components().find(|c| c.id == id).unwrap().label.clone();
+            (label(contact), label(coil))
+        };
+        let entries = vec![
+            (Vector2::new(0, 0), "K1".to_owned()),
+            (Vector2::new(200, 0), "K2".to_owned()),
+            // nothing there
+            (Vector2::new(400, 0), "K3".to_owned()),
+        ];
+
+        cad.start_simulation();
+        assert_eq!(cad.relabel(entries.clone()), None);
+        assert_eq!(labels(&cad), ("R".to_owned(), "R".to_owned()));
+        cad.stop_simulation();
+        assert_eq!(cad.relabel(entries), Some(2));
+        assert_eq!(labels(&cad), ("K1".to_owned(), "K2".to_owned()));
+    }
+
+    #[test]
+    fn zero_screen_size_keeps_the_view_finite() {
+        for center_origin in [false, true] {
+            let (mut cad, backend) = cad();
+            cad.set_center_origin(center_origin);
+            let mut io = Io::new();
+            io.set_screen_size(0, 0, 1.);
+            io.set_mouse_x(10.);
+            io.set_mouse_y(10.);
+            io.set_pinch(5.);
+            cad.new_frame(&mut io);
+            cad.draw();
+
+            let (top_left, bottom_right) = cad.transform.viewbox();
+            for f in top_left.iter().chain(bottom_right.iter()) {
+                assert!(f.is_finite());
+            }
+            assert!(cad.draw_list.projection_matrix().iter().all(|f| f.is_finite()));
+            assert!(backend.last().is_none());
+        }
+    }
+
+    #[test]
+    fn zoom_to_region_frames_the_region() {
+        let regions = [(100., 100., 500., 300.), (-2000., 0., 2000., 50.), (10., 10., 12., 11.)];
+        for center_origin in [false, true] {
+            for &(x1, y1, x2, y2) in &regions {
+                let (mut cad, backend) = cad();
+                cad.set_center_origin(center_origin);
+                frame(&mut cad, &backend, &mut io());
+                // corners given either way round
+                cad.zoom_to_region(x2, y1, x1, y2);
+
+                for corner in [Vector2::new(x1, y1), Vector2::new(x2, y2)] {
+                    let p = cad.transform.world_to_screen(corner);
+                    assert!((0. ..=800.).contains(&p.x), "{:?} off screen", p);
+                    assert!((0. ..=600.).contains(&p.y), "{:?} off screen", p);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lengths_read_out_in_the_configured_units() {
+        let (mut cad, backend) = cad();
+        // five grid steps
+        let span = 250.;
+        assert_eq!(cad.format_length(span), "5");
+        cad.set_units("mm", 2.54).unwrap();
+        assert_eq!(cad.format_length(span), "12.70 mm");
+        cad.set_units("in", 0.1).unwrap();
+        assert_eq!(cad.format_length(span), "0.500 in");
+
+        let mut io = io();
+        io.set_mouse_x(100.);
+        io.set_mouse_y(-50.);
+        frame(&mut cad, &backend, &mut io);
+        assert_eq!(cad.cursor_position(), "0.200 in, -0.100 in");
+    }
+
+    #[test]
+    fn loading_clears_the_selection() {
+        let (mut cad, _) = cad();
+        let r1 = add(&mut cad, symbol::Kind::CONTACT, 0, 0, "R1");
+        let r2 = add(&mut cad, symbol::Kind::CONTACT, 200, 100, "R2");
+        cad.set_selection(vec![r1, r2]);
+
+        let mut loaded = schematic::State::default();
+        for (x, y, label) in [(0, 0, "K1"), (200, 300, "K2")] {
+            loaded.add_component(schematic::Component::new(
+                Vector2::new(x, y),
+                symbol::Kind::CONTACT,
+                schematic::RotMirror::default(),
+                label.to_owned(),
+            ));
+        }
+        cad.replace_schematic(loaded);
+
+        assert!(cad.selection().is_empty());
+        assert!(!cad.align_selection("y").unwrap());
+        let mut positions = cad
+            .sch_state
+            .components()
+            .map(|c| (c.position.x, c.position.y))
+            .collect::<Vec<_>>();
+        positions.sort_unstable();
+        assert_eq!(positions, vec![(0, 0), (200, 300)]);
+    }
 }
@@ -2,15 +2,26 @@ use nalgebra::base::{Vector2, Vector4};
 
 pub type Color = Vector4<f32>;
 
+/// Vertex index type. Indices are relative to the `vtx_offset` of their `DrawCmd`.
+#[cfg(not(feature = "u16-index"))]
+pub type Index = u32;
+#[cfg(feature = "u16-index")]
+pub type Index = u16;
+
 #[derive(Debug, Clone)]
 pub struct DrawList {
     pub screen_size: Vector2<u32>,
     pub pixel_ratio: f32,
+    /// Screen position of the world origin, relative to the top-left corner of the screen or
+    /// to its center if `center_origin` is set.
     pub translate: Vector2<f32>,
+    pub center_origin: bool,
     pub scale: f32,
+    /// Multiplier on the number of segments circles and arcs are tessellated into.
+    pub circle_quality: f32,
     pub bg_color: Color,
     pub cmds: Vec<DrawCmd>,
-    idx_buffer: Vec<u32>,
+    idx_buffer: Vec<Index>,
     vtx_buffer: Vec<Vert>,
 }
 
@@ -20,7 +31,9 @@ impl DrawList {
             screen_size,
             pixel_ratio: 1.0,
             translate: Vector2::zeros(),
+            center_origin: false,
             scale: 1.,
+            circle_quality: 1.,
             bg_color: Color::new(1., 1., 1., 1.),
             cmds: vec![DrawCmd::default()],
             idx_buffer: vec![],
@@ -35,6 +48,18 @@ impl DrawList {
         self.vtx_buffer.clear();
     }
 
+    /// Pre-sizes the buffers for a schematic of the given size so they don't grow
+    /// repeatedly while it is drawn. The per-item costs are rough estimates.
+    pub fn reserve_for(&mut self, wire_count: usize, component_count: usize, glyph_count: usize) {
+        const WIRE: (usize, usize) = (16, 42);
+        const COMPONENT: (usize, usize) = (160, 420);
+        const GLYPH: (usize, usize) = (64, 168);
+        let vtx_count = wire_count * WIRE.0 + component_count * COMPONENT.0 + glyph_count * GLYPH.0;
+        let idx_count = wire_count * WIRE.1 + component_count * COMPONENT.1 + glyph_count * GLYPH.1;
+        self.idx_buffer.reserve(idx_count);
+        self.vtx_buffer.reserve(vtx_count);
+    }
+
     pub fn new_layer(&mut self) {
         self.cmds.push(DrawCmd {
             idx_offset: self.idx_buffer.len(),
@@ -43,18 +68,24 @@ impl DrawList {
         });
     }
 
+    /// Makes room for a primitive, starting a new command if its vertices wouldn't be
+    /// addressable from the current one.
     fn reserve(&mut self, idx_count: usize, vtx_count: usize) {
+        let vtx_offset = self.cmds.last().unwrap().vtx_offset;
+        if self.vtx_buffer.len() - vtx_offset + vtx_count > Index::MAX as usize + 1 {
+            self.new_layer();
+        }
         self.idx_buffer.reserve(idx_count);
         self.vtx_buffer.reserve(vtx_count);
     }
 
-    fn push_vert(&mut self, vert: Vert) -> u32 {
-        let idx = self.vtx_buffer.len() as u32;
+    fn push_vert(&mut self, vert: Vert) -> Index {
+        let idx = (self.vtx_buffer.len() - self.cmds.last().unwrap().vtx_offset) as Index;
         self.vtx_buffer.push(vert);
         idx
     }
 
-    fn push_elem(&mut self, a: u32, b: u32, c: u32) {
+    fn push_elem(&mut self, a: Index, b: Index, c: Index) {
         self.idx_buffer.push(a);
         self.idx_buffer.push(b);
         self.idx_buffer.push(c);
@@ -114,14 +145,15 @@ impl DrawList {
     }
 
     pub fn add_line(&mut self, p1: Vector2<f32>, p2: Vector2<f32>, col: Color, thickness: f32) {
+        // width in screen pixels; lines up to 1px wide are a bare quad without round caps
         let resolution = thickness * self.scale;
         let cap_segment_count = if resolution <= 1.0 {
             0
         } else {
             (resolution * 1.5).ceil() as usize
         };
-        let vtx_count = 4 + cap_segment_count;
-        let idx_count = (2 + cap_segment_count) * 3;
+        let vtx_count = 5 + cap_segment_count;
+        let idx_count = (3 + cap_segment_count) * 3;
         self.reserve(idx_count, vtx_count);
 
         let half_thickness = thickness * 0.5;
@@ -173,8 +205,8 @@ impl DrawList {
     pub fn add_circle(&mut self, p: Vector2<f32>, r: f32, col: Color, thickness: f32) {
         let resolution = thickness * self.scale;
         let half_thickness = thickness * 0.5;
-        let segment_count = (r + resolution).ceil() as usize;
-        let vtx_count = 2 * segment_count;
+        let segment_count = (((r + resolution) * self.circle_quality).ceil() as usize).max(1);
+        let vtx_count = 2 * (segment_count + 1);
         let idx_count = (2 * segment_count) * 3;
         self.reserve(idx_count, vtx_count);
 
@@ -206,6 +238,136 @@ impl DrawList {
         }
     }
 
+    /// Like `add_circle`, but only from `start` to `end` (radians, clockwise on screen).
+    pub fn add_arc(
+        &mut self,
+        p: Vector2<f32>,
+        r: f32,
+        start: f32,
+        end: f32,
+        col: Color,
+        thickness: f32,
+    ) {
+        let resolution = thickness * self.scale;
+        let half_thickness = thickness * 0.5;
+        let sweep = (end - start).abs();
+        let segment_count =
+            (((r + resolution) * self.circle_quality * sweep / (2. * std::f32::consts::PI)).ceil()
+                as usize)
+                .max(1);
+        let vtx_count = 2 * (segment_count + 1);
+        let idx_count = (2 * segment_count) * 3;
+        self.reserve(idx_count, vtx_count);
+
+        let r_o = r + half_thickness;
+        let r_i = r - half_thickness;
+        let v = Vector2::new(start.cos(), start.sin());
+        let mut v_o0 = self.push_vert(Vert {
+            pos: p + v.scale(r_o),
+            col,
+        });
+        let mut v_i0 = self.push_vert(Vert {
+            pos: p + v.scale(r_i),
+            col,
+        });
+        for i in 1..=segment_count {
+            let rad = start + (end - start) * i as f32 / segment_count as f32;
+            let v = Vector2::new(rad.cos(), rad.sin());
+            let v_o1 = self.push_vert(Vert {
+                pos: p + v.scale(r_o),
+                col,
+            });
+            let v_i1 = self.push_vert(Vert {
+                pos: p + v.scale(r_i),
+                col,
+            });
+            self.push_elem(v_o0, v_i0, v_o1);
+            self.push_elem(v_o1, v_i1, v_i0);
+            v_o0 = v_o1;
+            v_i0 = v_i1;
+        }
+    }
+
+    /// Filled circle.
+    pub fn add_disc(&mut self, p: Vector2<f32>, r: f32, col: Color) {
+        let max_segments = ((64. * self.circle_quality).ceil() as usize).max(8);
+        let segment_count =
+            ((r * self.scale * self.circle_quality).ceil() as usize).clamp(8, max_segments);
+        self.reserve(segment_count * 3, segment_count + 1);
+        let center = self.push_vert(Vert { pos: p, col });
+        let first = self.push_vert(Vert {
+            pos: p + Vector2::new(r, 0.),
+            col,
+        });
+        let mut prev = first;
+        for i in 1..segment_count {
+            let rad = i as f32 * 2.0 / segment_count as f32 * std::f32::consts::PI;
+            let v = self.push_vert(Vert {
+                pos: p + Vector2::new(rad.cos(), rad.sin()).scale(r),
+                col,
+            });
+            self.push_elem(center, prev, v);
+            prev = v;
+        }
+        self.push_elem(center, prev, first);
+    }
+
+    /// Fills a convex polygon as a triangle fan.
+    pub fn add_filled_polygon(&mut self, points: &[Vector2<f32>], col: Color) {
+        if points.len() < 3 {
+            return;
+        }
+        self.reserve((points.len() - 2) * 3, points.len());
+        let first = self.push_vert(Vert { pos: points[0], col });
+        let mut prev = self.push_vert(Vert { pos: points[1], col });
+        for &pos in &points[2..] {
+            let v = self.push_vert(Vert { pos, col });
+            self.push_elem(first, prev, v);
+            prev = v;
+        }
+    }
+
+    /// Fills the rectangle from `min` to `max` with corners rounded by `radius`.
+    pub fn add_filled_rect(&mut self, min: Vector2<f32>, max: Vector2<f32>, radius: f32, col: Color) {
+        let radius = radius.min((max.x - min.x) * 0.5).min((max.y - min.y) * 0.5).max(0.);
+        let corner_segment_count = ((radius * self.scale).ceil() as usize).clamp(1, 16);
+        let vtx_count = 1 + 4 * (corner_segment_count + 1);
+        let idx_count = vtx_count * 3;
+        self.reserve(idx_count, vtx_count);
+
+        let center = self.push_vert(Vert {
+            pos: (min + max).scale(0.5),
+            col,
+        });
+        let corners = [
+            Vector2::new(max.x - radius, max.y - radius),
+            Vector2::new(min.x + radius, max.y - radius),
+            Vector2::new(min.x + radius, min.y + radius),
+            Vector2::new(max.x - radius, min.y + radius),
+        ];
+        let mut first = None;
+        let mut prev = None;
+        for (quadrant, corner) in corners.iter().enumerate() {
+            for i in 0..=corner_segment_count {
+                let rad = (quadrant as f32 + i as f32 / corner_segment_count as f32)
+                    * std::f32::consts::FRAC_PI_2;
+                let v = self.push_vert(Vert {
+                    pos: corner + Vector2::new(rad.cos(), rad.sin()).scale(radius),
+                    col,
+                });
+                if let Some(prev) = prev {
+                    self.push_elem(center, prev, v);
+                } else {
+                    first = Some(v);
+                }
+                prev = Some(v);
+            }
+        }
+        if let (Some(first), Some(last)) = (first, prev) {
+            self.push_elem(center, last, first);
+        }
+    }
+
     #[allow(clippy::many_single_char_names)]
     pub fn add_square(&mut self, p: Vector2<f32>, size: f32, col: Color) {
         let half_size = size * 0.5;
@@ -229,6 +391,30 @@ impl DrawList {
         self.push_elem(a, b, c);
         self.push_elem(b, c, d);
     }
+
+    /// Column-major matrix from world coordinates to clip space.
+    pub fn projection_matrix(&self) -> [f32; 16] {
+        let w = self.screen_size.x.max(1) as f32;
+        let h = self.screen_size.y.max(1) as f32;
+        let scale = self.scale;
+        let translate = if self.center_origin {
+            self.translate + Vector2::new(w, h).scale(0.5)
+        } else {
+            self.translate
+        };
+        let sx = (2. / w) * scale;
+        let sy = (2. / h) * scale;
+        let npx = 2. * translate.x / w + 1. / w;
+        let npy = -2. * translate.y / h + 1. / h;
+        #[rustfmt::skip]
+        let projection = [
+            sx, 0., 0., 0.,
+            0., -sy, 0., 0.,
+            0., 0., -1., 0.,
+            npx - 1., npy + 1., 0., 1.,
+        ];
+        projection
+    }
 }
 
 impl DrawList {
@@ -242,7 +428,7 @@ impl DrawList {
         }
     }
 
-    pub fn indices(&self) -> &[u32] {
+    pub fn indices(&self) -> &[Index] {
         &self.idx_buffer
     }
 }
@@ -295,3 +481,51 @@ pub struct DrawCmd {
     pub idx_offset: usize,
     pub num_of_elems: usize,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_matrix_eq(actual: [f32; 16], expected: [f32; 16]) {
+        for (a, e) in actual.iter().zip(&expected) {
+            assert!((a - e).abs() < 1e-6, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn projection_matrix_for_a_known_transform() {
+        let mut draw_list = DrawList::new(Vector2::new(200, 100));
+        draw_list.scale = 2.;
+        draw_list.translate = Vector2::new(10., 20.);
+        #[rustfmt::skip]
+        let expected = [
+            0.02, 0., 0., 0.,
+            0., -0.04, 0., 0.,
+            0., 0., -1., 0.,
+            -0.895, 0.61, 0., 1.,
+        ];
+        assert_matrix_eq(draw_list.projection_matrix(), expected);
+
+        // the same view with the origin at the screen center
+        draw_list.center_origin = true;
+        draw_list.translate = Vector2::new(-90., -30.);
+        assert_matrix_eq(draw_list.projection_matrix(), expected);
+    }
+
+    #[test]
+    fn circle_quality_scales_the_vertex_count() {
+        let vertices = |quality: f32, draw: &dyn Fn(&mut DrawList)| {
+            let mut draw_list = DrawList::new(Vector2::new(200, 100));
+            draw_list.circle_quality = quality;
+            draw(&mut draw_list);
+            draw_list.vertices().len()
+        };
+        let col = Color::new(0., 0., 0., 1.);
+        let circle = |d: &mut DrawList| d.add_circle(Vector2::new(50., 50.), 10., col, 1.);
+        let disc = |d: &mut DrawList| d.add_disc(Vector2::new(50., 50.), 20., col);
+        for draw in [&circle as &dyn Fn(&mut DrawList), &disc] {
+            assert!(vertices(2., draw) > vertices(1., draw));
+            assert!(vertices(0.5, draw) < vertices(1., draw));
+        }
+    }
+}
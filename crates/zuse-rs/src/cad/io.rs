@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use nalgebra::Vector2;
 use wasm_bindgen::prelude::*;
 
@@ -21,6 +23,63 @@ pub enum Event {
     MouseUp(u8),
     Click(u8),
     DoubleClick(u8),
+    /// The canvas lost focus, so keys may have been released without a `Keyup`.
+    Blur,
+}
+
+/// Modifier keys currently held.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+}
+
+impl Modifiers {
+    pub fn update(&mut self, event: &Event) {
+        let (key, held) = match event {
+            Event::Keydown(key) => (key, true),
+            Event::Keyup(key) => (key, false),
+            Event::Blur => {
+                *self = Self::default();
+                return;
+            }
+            _ => return,
+        };
+        match key.as_str() {
+            "Shift" => self.shift = held,
+            "Control" => self.ctrl = held,
+            "Alt" => self.alt = held,
+            _ => {}
+        }
+    }
+}
+
+/// Keys currently held, for suppressing auto-repeated `Keydown`s.
+#[derive(Debug, Default)]
+pub struct HeldKeys {
+    keys: HashSet<String>,
+}
+
+impl HeldKeys {
+    /// Returns `false` for a `Keydown` of a key that is already held. Arrow keys repeat on
+    /// purpose and are always let through.
+    pub fn update(&mut self, event: &Event) -> bool {
+        match event {
+            Event::Keydown(key) => {
+                key.starts_with("Arrow") || self.keys.insert(key.to_lowercase())
+            }
+            Event::Keyup(key) => {
+                self.keys.remove(&key.to_lowercase());
+                true
+            }
+            Event::Blur => {
+                self.keys.clear();
+                true
+            }
+            _ => true,
+        }
+    }
 }
 
 #[wasm_bindgen]
@@ -108,6 +167,11 @@ impl Io {
         self.events.push(Event::DoubleClick(button));
     }
 
+    #[wasm_bindgen(js_name = pushBlur)]
+    pub fn push_blur(&mut self) {
+        self.events.push(Event::Blur);
+    }
+
     #[wasm_bindgen(js_name = setScreenSize)]
     pub fn set_screen_size(&mut self, x: u32, y: u32, pixel_ratio: f32) {
         self.screen_size = Vector2::new(x, y);
@@ -0,0 +1,79 @@
+use std::collections::VecDeque;
+
+/// Samples kept per probe.
+pub const HISTORY: usize = 256;
+
+/// A simulator state sampled once per pass for the waveform view.
+pub struct Probe {
+    name: String,
+    samples: VecDeque<Option<bool>>,
+}
+
+impl Probe {
+    /// `name` is a state name such as `R1.A`. A bare label stands for the `.A` state of that
+    /// component, i.e. whether the relay is energized or the contact is made.
+    pub fn new(name: String) -> Self {
+        let name = if name.contains('.') {
+            name
+        } else {
+            format!("{}.A", name)
+        };
+        Self {
+            name,
+            samples: VecDeque::with_capacity(HISTORY),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn sample(&mut self, circuit: &zuse_core::Circuit) {
+        if self.samples.len() == HISTORY {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(circuit.get_state(&self.name));
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Oldest first.
+    pub fn samples(&self) -> impl Iterator<Item = Option<bool>> + '_ {
+        self.samples.iter().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circuit() -> zuse_core::Circuit {
+        let netlist = zuse_core::net::Netlist {
+            relays: vec![],
+            switches: vec![],
+        };
+        zuse_core::compile(&netlist).build()
+    }
+
+    #[test]
+    fn a_bare_label_probes_its_a_state() {
+        assert_eq!(Probe::new("R1".to_owned()).name(), "R1.A");
+        assert_eq!(Probe::new("R1.B".to_owned()).name(), "R1.B");
+    }
+
+    #[test]
+    fn history_is_capped() {
+        let circuit = circuit();
+        let mut probe = Probe::new("R1".to_owned());
+        for _ in 0..HISTORY + 10 {
+            probe.sample(&circuit);
+        }
+        assert_eq!(probe.samples().count(), HISTORY);
+
+        probe.clear();
+        probe.sample(&circuit);
+        assert_eq!(probe.samples().collect::<Vec<_>>(), vec![None]);
+    }
+}
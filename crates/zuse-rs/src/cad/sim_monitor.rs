@@ -0,0 +1,69 @@
+use serde::Serialize;
+use zuse_core::net::Netlist;
+
+/// Counts simulation passes until the observed states stop changing.
+///
+/// The core doesn't report its own iterations, so every `simulate()` call is one pass and
+/// the circuit has settled once a pass leaves all relay and switch states unchanged.
+pub struct SimMonitor {
+    names: Vec<String>,
+    last: Vec<Option<bool>>,
+    metrics: SimMetrics,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct SimMetrics {
+    /// Passes that changed some state since the last input.
+    pub passes: u32,
+    pub converged: bool,
+}
+
+impl SimMonitor {
+    pub fn new(netlist: &Netlist) -> Self {
+        let mut names = netlist
+            .relays
+            .iter()
+            .flat_map(|relay| [relay.a.clone(), relay.b.clone()])
+            .chain(netlist.switches.iter().map(|switch| switch.state.clone()))
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        Self {
+            names,
+            last: vec![],
+            metrics: SimMetrics::default(),
+        }
+    }
+
+    /// Names of the observed relay and switch states, in snapshot order.
+    #[cfg_attr(not(feature = "wasm"), allow(dead_code))]
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn snapshot(&self, circuit: &zuse_core::Circuit) -> Vec<Option<bool>> {
+        self.names.iter().map(|name| circuit.get_state(name)).collect()
+    }
+
+    /// Records the states after a pass.
+    pub fn observe(&mut self, circuit: &zuse_core::Circuit) {
+        let states = self.snapshot(circuit);
+        if states == self.last {
+            self.metrics.converged = true;
+        } else {
+            self.metrics.passes += 1;
+            self.metrics.converged = false;
+            self.last = states;
+        }
+    }
+
+    /// Starts counting again, e.g. after the user flipped a switch.
+    pub fn reset(&mut self) {
+        self.metrics = SimMetrics::default();
+    }
+
+    #[cfg_attr(not(feature = "wasm"), allow(dead_code))]
+    pub fn metrics(&self) -> SimMetrics {
+        self.metrics
+    }
+}
@@ -0,0 +1,251 @@
+//! The schematic as a standalone SVG document. Each component is a `<g>` drawn in symbol
+//! coordinates under a `transform` for its position and orientation, and carries enough to
+//! rebuild it: `data-symbol`, `data-label`, `data-x`, `data-y` and `data-rot-mirror` (see
+//! `RotMirror`'s `Display`).
+
+use std::fmt::Write;
+
+use nalgebra::Vector2;
+use rstar::{Envelope, RTreeObject, AABB};
+
+use super::{Color, Theme};
+use crate::schematic::{self, State};
+use crate::symbol::Draw;
+
+/// Space left around the drawing, in world units.
+const MARGIN: i32 = 50;
+
+pub fn export(state: &State, theme: &Theme, text_height: f32) -> String {
+    let everything = AABB::from_corners([i32::MIN; 2], [i32::MAX; 2]);
+    let wires = state.wires_iter(&everything).collect::<Vec<_>>();
+    let bounds = wires
+        .iter()
+        .map(|wire| wire.envelope())
+        .chain(state.components().map(|component| component.envelope()))
+        .reduce(|a, b| a.merged(&b))
+        .unwrap_or_else(|| AABB::from_point([0, 0]));
+    let (min, max) = (bounds.lower(), bounds.upper());
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}">"#,
+        min[0] - MARGIN,
+        min[1] - MARGIN,
+        max[0] - min[0] + MARGIN * 2,
+        max[1] - min[1] + MARGIN * 2
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r#"<g stroke="{}" stroke-width="6" stroke-linecap="round">"#,
+        css(theme.wire)
+    )
+    .unwrap();
+    for wire in wires {
+        let p1 = nalgebra::convert(Vector2::from(wire.from));
+        let p2 = nalgebra::convert(Vector2::from(wire.to));
+        line(&mut svg, p1, p2, 6.);
+    }
+    for (p, count) in state.junctions_iter(&everything) {
+        if count >= 3 {
+            let col = css(theme.wire);
+            writeln!(svg, r#"<circle cx="{}" cy="{}" r="20" fill="{}"/>"#, p.x, p.y, col).unwrap();
+        }
+    }
+    svg.push_str("</g>\n");
+    for component in state.components_sorted() {
+        component_group(&mut svg, component, theme, text_height);
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn component_group(
+    svg: &mut String,
+    component: &schematic::Component,
+    theme: &Theme,
+    text_height: f32,
+) {
+    let schematic::Component {
+        position,
+        rot_mirror,
+        ..
+    } = component;
+    // SVG's matrix(a b c d e f) maps (x, y) to (ax + cy + e, bx + dy + f)
+    let m = rot_mirror.apply(Vector2::new(1, 0));
+    let n = rot_mirror.apply(Vector2::new(0, 1));
+    write!(
+        svg,
+        r#"<g data-symbol="{}" data-label="{}" data-x="{}" data-y="{}" data-rot-mirror="{}" "#,
+        escape(component.symbol.def().id()),
+        escape(&component.label),
+        position.x,
+        position.y,
+        rot_mirror
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r#"transform="matrix({} {} {} {} {} {})" fill="none" stroke="{}" stroke-linecap="round">"#,
+        m.x,
+        m.y,
+        n.x,
+        n.y,
+        position.x,
+        position.y,
+        css(theme.component)
+    )
+    .unwrap();
+    for draw in component.symbol.def().draw(&|_| None) {
+        match draw {
+            Draw::Line(p1, p2, thickness) => line(svg, p1, p2, thickness),
+            Draw::Circle(p, r, thickness) => {
+                writeln!(
+                    svg,
+                    r#"<circle cx="{}" cy="{}" r="{}" stroke-width="{}"/>"#,
+                    p.x, p.y, r, thickness
+                )
+                .unwrap();
+            }
+            Draw::FilledPolygon(points) => {
+                let points = points
+                    .iter()
+                    .map(|p| format!("{},{}", p.x, p.y))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let col = css(theme.component);
+                writeln!(svg, r#"<polygon points="{}" fill="{}" stroke="none"/>"#, points, col)
+                    .unwrap();
+            }
+            Draw::Arc(p, r, start, end, thickness) => {
+                let at = |angle: f32| p + Vector2::new(angle.cos(), angle.sin()).scale(r);
+                let (from, to) = (at(start), at(end));
+                let large = (end - start).abs() > std::f32::consts::PI;
+                writeln!(
+                    svg,
+                    r#"<path d="M {} {} A {} {} 0 {} 1 {} {}" stroke-width="{}"/>"#,
+                    from.x, from.y, r, r, large as u8, to.x, to.y, thickness
+                )
+                .unwrap();
+            }
+        }
+    }
+    svg.push_str("</g>\n");
+    // labels stay upright, so they are placed outside the rotated group
+    writeln!(
+        svg,
+        r#"<text x="{}" y="{}" font-size="{}" font-family="monospace" fill="{}">{}</text>"#,
+        position.x + 50,
+        position.y,
+        text_height,
+        css(theme.text),
+        escape(&component.label)
+    )
+    .unwrap();
+}
+
+fn line(svg: &mut String, p1: Vector2<f32>, p2: Vector2<f32>, thickness: f32) {
+    writeln!(
+        svg,
+        r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke-width="{}"/>"#,
+        p1.x, p1.y, p2.x, p2.y, thickness
+    )
+    .unwrap();
+}
+
+fn css(col: Color) -> String {
+    let channel = |f: f32| (f.clamp(0., 1.) * 255.).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", channel(col.x), channel(col.y), channel(col.z))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schematic::{Component, RotMirror};
+    use crate::symbol;
+
+    /// The value of `name` in the first tag that has it.
+    fn attribute<'a>(svg: &'a str, name: &str) -> &'a str {
+        let start = svg.find(&format!(r#" {}=""#, name)).unwrap() + name.len() + 3;
+        let len = svg[start..].find('"').unwrap();
+        &svg[start..start + len]
+    }
+
+    #[test]
+    fn component_orientation_survives_export() {
+        let rot_mirror = RotMirror::default().rotate_r().mirror();
+        let mut state = State::default();
+        let position = Vector2::new(200, 300);
+        let contact = Component::new(position, symbol::Kind::CONTACT, rot_mirror, "R1".to_owned());
+        state.add_component(contact.clone());
+
+        let svg = export(&state, &Theme::default(), 50.);
+        let symbol = symbol::Kind::from_id(attribute(&svg, "data-symbol")).unwrap();
+        let position = Vector2::new(
+            attribute(&svg, "data-x").parse().unwrap(),
+            attribute(&svg, "data-y").parse().unwrap(),
+        );
+        let imported = Component::new(
+            position,
+            symbol,
+            attribute(&svg, "data-rot-mirror").parse().unwrap(),
+            attribute(&svg, "data-label").to_owned(),
+        );
+        assert_eq!(imported.rot_mirror, rot_mirror);
+        assert_eq!(imported.position, contact.position);
+        assert_eq!(imported.envelope(), contact.envelope());
+        assert_eq!(attribute(&svg, "transform"), "matrix(0 1 1 0 200 300)");
+    }
+
+    struct Quoted(symbol::Pads);
+
+    impl symbol::SymbolDef for Quoted {
+        fn id(&self) -> &str {
+            r#"Say "hi" & <wave>"#
+        }
+
+        fn default_label(&self) -> &str {
+            "Q"
+        }
+
+        fn aabb(&self) -> rstar::AABB<[i32; 2]> {
+            rstar::AABB::from_corners([-50, -50], [50, 50])
+        }
+
+        fn pads(&self) -> &symbol::Pads {
+            &self.0
+        }
+
+        fn can_rotate(&self) -> bool {
+            false
+        }
+
+        fn can_mirror(&self) -> bool {
+            false
+        }
+
+        fn draw(&self, _state: &dyn Fn(&str) -> Option<bool>) -> Vec<symbol::Draw> {
+            vec![]
+        }
+
+        fn build_netlist(&self, _: &str, _: &[String], _: &mut zuse_core::net::Netlist) {}
+    }
+
+    #[test]
+    fn symbol_id_is_escaped() {
+        let kind = symbol::register(Box::new(Quoted(symbol::Pads::new(vec![])))).unwrap();
+        let mut state = State::default();
+        let origin = Vector2::new(0, 0);
+        state.add_component(Component::new(origin, kind, RotMirror::default(), "Q1".to_owned()));
+
+        let svg = export(&state, &Theme::default(), 50.);
+        assert_eq!(attribute(&svg, "data-symbol"), "Say &quot;hi&quot; &amp; &lt;wave&gt;");
+    }
+}
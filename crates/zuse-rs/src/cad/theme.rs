@@ -0,0 +1,38 @@
+use serde::Deserialize;
+
+use super::Color;
+
+/// Colors of everything `Cad` draws, as `[r, g, b, a]` in 0 to 1. Previews use the color of
+/// what they preview at half opacity.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub background: Color,
+    pub wire: Color,
+    pub component: Color,
+    pub text: Color,
+    pub cursor: Color,
+    pub selection: Color,
+    /// The wire segment under the pointer.
+    pub hover: Color,
+    /// The highlighted net.
+    pub highlight: Color,
+    /// Alignment guides while moving a component.
+    pub guide: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            background: Color::new(1., 1., 1., 1.),
+            wire: Color::new(0., 132. / 255., 0., 1.),
+            component: Color::new(0.51, 0., 0., 1.),
+            text: Color::new(0., 0., 0., 1.),
+            cursor: Color::new(0., 0., 0., 1.),
+            selection: Color::new(0., 0.4, 1., 0.8),
+            hover: Color::new(0., 0.4, 1., 0.4),
+            highlight: Color::new(1., 0.55, 0., 1.),
+            guide: Color::new(0., 0.4, 1., 0.6),
+        }
+    }
+}
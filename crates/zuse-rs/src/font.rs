@@ -9,13 +9,14 @@ pub type Glyph = Vec<Segment>;
 
 pub struct Font {
     advance: f32,
+    height: f32,
     glyphs: HashMap<char, Glyph>,
 }
 
 type GlyphData = Vec<((f32, f32), (f32, f32))>;
 
 impl Font {
-    fn load(json: &[u8], advance: f32) -> Self {
+    fn load(json: &[u8], advance: f32, height: f32) -> Self {
         let glyphs: HashMap<char, GlyphData> = serde_json::from_slice(json).unwrap();
         let glyphs = glyphs
             .into_iter()
@@ -29,7 +30,11 @@ impl Font {
                 )
             })
             .collect();
-        Self { advance, glyphs }
+        Self {
+            advance,
+            height,
+            glyphs,
+        }
     }
 
     pub fn glyph(&self, char: char) -> Option<&Glyph> {
@@ -39,8 +44,18 @@ impl Font {
     pub fn advance(&self) -> f32 {
         self.advance
     }
+
+    /// Factor from glyph units to world units for text of the given height.
+    pub fn scale(&self, text_height: f32) -> f32 {
+        text_height / self.height
+    }
+
+    /// Width of `text` in world units.
+    pub fn measure(&self, text: &str, text_height: f32) -> f32 {
+        text.chars().count() as f32 * self.advance * self.scale(text_height)
+    }
 }
 
 lazy_static::lazy_static! {
-    pub static ref FONT: Font = Font::load(FONT_JSON, 11.0);
+    pub static ref FONT: Font = Font::load(FONT_JSON, 11.0, 11.0);
 }
@@ -1,14 +1,17 @@
 mod backend;
-mod schematic;
+pub mod schematic;
 mod cad;
-mod symbol;
+pub mod symbol;
 mod font;
-pub use cad::{Cad, Io};
+pub mod logger;
+pub use backend::{Backend, RecordingBackend};
+pub use cad::{Cad, ComponentMetadata, DrawList, Io, Theme};
+#[cfg(feature = "wasm")]
 pub use backend::GlowBackend;
 
 #[macro_export]
 macro_rules! log {
     ( $( $t:tt )* ) => {
-        web_sys::console::log_1(&format!( $( $t )* ).into());
+        $crate::logger::log($crate::logger::Level::Info, &format!( $( $t )* ));
     }
 }
@@ -0,0 +1,70 @@
+//! Where `log!` output goes: the browser console unless the host installs a handler.
+
+use std::cell::RefCell;
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Info,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Record<'a> {
+    pub level: Level,
+    pub message: &'a str,
+}
+
+type Sink = Box<dyn Fn(&Record)>;
+
+thread_local! {
+    static SINK: RefCell<Option<Sink>> = RefCell::new(None);
+}
+
+/// Routes records to `sink`, or back to the console for `None`.
+pub fn set_sink(sink: Option<Sink>) {
+    SINK.with(|s| *s.borrow_mut() = sink);
+}
+
+pub fn log(level: Level, message: &str) {
+    let record = Record { level, message };
+    SINK.with(|s| match &*s.borrow() {
+        Some(sink) => sink(&record),
+        None => console(&record),
+    });
+}
+
+#[cfg(feature = "wasm")]
+fn console(record: &Record) {
+    match record.level {
+        Level::Info => web_sys::console::log_1(&record.message.into()),
+    }
+}
+
+#[cfg(not(feature = "wasm"))]
+fn console(record: &Record) {
+    eprintln!("[{:?}] {}", record.level, record.message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn log_goes_to_the_installed_sink() {
+        let records = Rc::new(RefCell::new(vec![]));
+        let sink_records = Rc::clone(&records);
+        set_sink(Some(Box::new(move |record: &Record| {
+            sink_records
+                .borrow_mut()
+                .push((record.level, record.message.to_owned()));
+        })));
+        crate::log!("placed {}", 3);
+        set_sink(None);
+        crate::log!("not captured");
+
+        assert_eq!(*records.borrow(), vec![(Level::Info, "placed 3".to_owned())]);
+    }
+}
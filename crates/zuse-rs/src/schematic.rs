@@ -1,8 +1,8 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use itertools::Itertools;
 use nalgebra::Vector2;
-use rstar::{AABB, RTree, RTreeObject, primitives::{GeomWithData, Line}};
+use rstar::{AABB, Envelope, RTree, RTreeObject, primitives::{GeomWithData, Line}};
 use serde::{Deserialize, Serialize};
 
 use crate::symbol;
@@ -105,9 +105,15 @@ pub trait Wire {
         p
     }
 
+    /// Never overflows, whichever way round the ends are.
     #[inline]
     fn len(&self) -> u32 {
-        (self.end() - self.start()) as u32
+        self.end().abs_diff(self.start())
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.start() == self.end()
     }
 
     fn aabb(&self) -> AABB<[i32; 2]> {
@@ -243,14 +249,54 @@ impl Default for RotMirror {
     }
 }
 
+/// The matrix entries row by row, e.g. `0 -1 1 0`, as in the `data-rot-mirror` of an SVG
+/// export.
+impl std::fmt::Display for RotMirror {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let RotMirror(a, b, c, d) = self;
+        write!(f, "{} {} {} {}", a, b, c, d)
+    }
+}
+
+impl std::str::FromStr for RotMirror {
+    type Err = String;
+
+    /// Parses the `Display` form, accepting only the eight rotations and mirrorings.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid orientation: {}", s);
+        let entries = s
+            .split_whitespace()
+            .map(|n| n.parse::<i8>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let rot_mirror = match entries[..] {
+            [a, b, c, d] => RotMirror(a, b, c, d),
+            _ => return Err(invalid()),
+        };
+        let rotations =
+            std::iter::successors(Some(RotMirror::default()), |r| Some(r.rotate_r())).take(4);
+        if rotations.flat_map(|r| [r, r.mirror()]).any(|r| r == rot_mirror) {
+            Ok(rot_mirror)
+        } else {
+            Err(invalid())
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Component {
     pub position: Vector2<i32>,
     aabb: AABB<[i32; 2]>,
     pub symbol: symbol::Kind,
     pub rot_mirror: RotMirror,
+    /// Designator, which also prefixes the component's simulator states.
     #[serde(default)]
     pub label: String,
+    /// Free-form annotation shown under the label.
+    #[serde(default)]
+    pub value: String,
+    /// Stable handle, unlike the label. 0 until added to a `State`.
+    #[serde(default)]
+    pub id: u32,
 }
 
 impl Component {
@@ -270,11 +316,25 @@ impl Component {
             symbol,
             rot_mirror,
             label,
+            value: String::new(),
+            id: 0,
         }
     }
 
     fn rot_mirror(&self, rot_mirror: RotMirror) -> Self {
-        Self::new(self.position, self.symbol, rot_mirror, self.label.clone())
+        Self {
+            id: self.id,
+            value: self.value.clone(),
+            ..Self::new(self.position, self.symbol, rot_mirror, self.label.clone())
+        }
+    }
+
+    fn position(&self, position: Vector2<i32>) -> Self {
+        Self {
+            id: self.id,
+            value: self.value.clone(),
+            ..Self::new(position, self.symbol, self.rot_mirror, self.label.clone())
+        }
     }
 
     fn pads(&self) -> impl Iterator<Item = symbol::Pad> {
@@ -282,9 +342,96 @@ impl Component {
     }
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct Net {
+    pub id: u32,
+    /// Wire endpoints and pad positions on the net.
+    pub points: Vec<[i32; 2]>,
+    pub pads: Vec<NetPad>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NetPad {
+    /// Component id.
+    pub component: u32,
+    pub label: String,
+    pub pad: String,
+}
+
+fn ord(a: i32, b: i32) -> (i32, i32) {
+    (a.min(b), a.max(b))
+}
+
+/// Takes `next_id` unless the ids have run up to `u32::MAX`, which is never used so that the
+/// next one always fits, and then the lowest id that isn't `taken`.
+fn take_id(next_id: &mut u32, taken: impl Fn(u32) -> bool) -> u32 {
+    if *next_id < u32::MAX {
+        let id = (*next_id).max(1);
+        *next_id = id + 1;
+        id
+    } else {
+        (1..u32::MAX).find(|&id| !taken(id)).expect("out of component ids")
+    }
+}
+
+/// `label` if it isn't in `taken`, otherwise its prefix with the first free number, e.g.
+/// `R3` for `R1` when `R1` and `R2` are taken.
+fn unique_label(label: &str, taken: &HashSet<String>) -> String {
+    if !taken.contains(label) {
+        return label.to_string();
+    }
+    let prefix = label.trim_end_matches(|c: char| c.is_ascii_digit());
+    (1..)
+        .map(|n| format!("{}{}", prefix, n))
+        .find(|candidate| !taken.contains(candidate))
+        .unwrap()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PadConnection {
+    pub name: String,
+    pub position: [i32; 2],
+    /// Whether a wire or another pad meets the pad.
+    pub connected: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A design rule violation found by `State::drc`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Issue {
+    pub severity: Severity,
+    /// Machine-readable kind, e.g. `"floating_pad"`.
+    pub kind: &'static str,
+    pub message: String,
+    pub position: [i32; 2],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub component: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NetlistStats {
+    pub nets: usize,
+    pub relays: usize,
+    pub switches: usize,
+    pub floating_pads: usize,
+    pub shorted_switches: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+}
+
 impl PartialEq for Component {
     fn eq(&self, other: &Self) -> bool {
-        self.position == other.position && self.rot_mirror == other.rot_mirror
+        self.position == other.position && self.rot_mirror == other.rot_mirror && self.id == other.id
     }
 }
 
@@ -296,18 +443,31 @@ impl rstar::RTreeObject for Component {
     }
 }
 
+/// Components and the wires among them, positioned relative to the snippet's own origin.
+#[derive(Serialize, Deserialize)]
+pub struct Snippet {
+    wires: Vec<Line<[i32; 2]>>,
+    components: Vec<Component>,
+}
+
 #[derive(Default, Serialize, Deserialize)]
 pub struct State {
     wires: RTree<Line<[i32; 2]>>,
     junctions: Junctions,
     components: RTree<Component>,
+    #[serde(skip)]
+    next_id: u32,
+    /// Bumped on every edit so derived data can be cached.
+    #[serde(skip)]
+    revision: u64,
 }
 
 impl State {
     pub fn add_wire<W: Wire>(&mut self, new_wire: W) {
-        if new_wire.len() == 0 {
+        if new_wire.is_empty() {
             return;
         }
+        self.revision += 1;
 
         let wires = self
             .wires
@@ -315,8 +475,9 @@ impl State {
             .filter(|&&wire| W::Axis::is_para(wire))
             .cloned()
             .collect::<Vec<_>>();
-        let mut start = new_wire.start();
-        let mut end = new_wire.end();
+        // callers are expected to pass `start <= end`, but don't rely on it
+        let mut start = new_wire.start().min(new_wire.end());
+        let mut end = new_wire.start().max(new_wire.end());
         for wire in wires {
             if start > W::Axis::start(wire) {
                 start = W::Axis::start(wire);
@@ -372,6 +533,18 @@ impl State {
         }
     }
 
+    fn add_wire_line(&mut self, line: Line<[i32; 2]>) {
+        if Horizontal::is_para(line) {
+            let x1 = line.from[0].min(line.to[0]);
+            let x2 = line.from[0].max(line.to[0]);
+            self.add_wire(WireH { y: line.from[1], x1, x2 });
+        } else {
+            let y1 = line.from[1].min(line.to[1]);
+            let y2 = line.from[1].max(line.to[1]);
+            self.add_wire(WireV { x: line.from[0], y1, y2 });
+        }
+    }
+
     fn normalize_wires(&mut self, dirty_junctions: &[[i32; 2]]) {
         for &junction in dirty_junctions {
             let (wires_h, wires_v): (Vec<_>, Vec<_>) = self
@@ -404,6 +577,7 @@ impl State {
     }
 
     pub fn delete_at_point(&mut self, p: [i32; 2], size: i32) {
+        self.revision += 1;
         let aabb = AABB::from_corners([p[0] - size, p[1] - size], [p[0] + size, p[1] + size]);
         let wires_to_delete = self
             .wires
@@ -433,7 +607,70 @@ impl State {
         self.normalize_wires(&dirty_junctions);
     }
 
+    pub fn wire_at_point(&self, p: [i32; 2], size: i32) -> Option<Line<[i32; 2]>> {
+        let aabb = AABB::from_corners([p[0] - size, p[1] - size], [p[0] + size, p[1] + size]);
+        self.wires.locate_in_envelope_intersecting(&aabb).next().cloned()
+    }
+
+    /// Moves `wire` perpendicular to itself by `offset`.
+    ///
+    /// With `stretch`, whatever is attached to the endpoints stays connected: a wire that
+    /// merely turns the corner is stretched to the new endpoint, and anything else (pads,
+    /// tees) is bridged with a new perpendicular wire.
+    pub fn drag_wire(&mut self, wire: Line<[i32; 2]>, offset: i32, stretch: bool) {
+        if offset == 0 || self.wires.remove(&wire).is_none() {
+            return;
+        }
+        self.revision += 1;
+        let horizontal = Horizontal::is_para(wire);
+        let perp_axis = if horizontal {
+            Horizontal::PERP_AXIS
+        } else {
+            Vertical::PERP_AXIS
+        };
+        let moved = |mut p: [i32; 2]| {
+            p[perp_axis] += offset;
+            p
+        };
+        let mut dirty_junctions = vec![];
+        let mut legs = vec![];
+        for p in [wire.from, wire.to] {
+            let rc = self.junctions.decr_by(p, 1);
+            if rc == 2 {
+                dirty_junctions.push(p);
+            }
+            if !stretch || rc == 0 {
+                continue;
+            }
+            let ending_wires = self
+                .wires
+                .locate_in_envelope_intersecting(&AABB::from_point(p))
+                .filter(|w| w.from == p || w.to == p)
+                .cloned()
+                .collect::<Vec<_>>();
+            match ending_wires.as_slice() {
+                [corner] if rc == 1 && Horizontal::is_para(*corner) != horizontal => {
+                    // L-bend: stretch the other leg instead of adding a new one
+                    let far = if corner.from == p { corner.to } else { corner.from };
+                    self.wires.remove(corner);
+                    self.junctions.decr_by(p, 1);
+                    if self.junctions.decr_by(far, 1) == 2 {
+                        dirty_junctions.push(far);
+                    }
+                    legs.push(Line::new(far, moved(p)));
+                }
+                _ => legs.push(Line::new(p, moved(p))),
+            }
+        }
+        self.normalize_wires(&dirty_junctions);
+        self.add_wire_line(Line::new(moved(wire.from), moved(wire.to)));
+        for leg in legs {
+            self.add_wire_line(leg);
+        }
+    }
+
     fn delete_component(&mut self, component: &Component, dirty_junctions: &mut Vec<[i32; 2]>) {
+        self.revision += 1;
         self.components.remove(component);
         for pad in component.pads() {
             let p = pad.position.into();
@@ -459,7 +696,10 @@ impl State {
         self.normalize_wires(&dirty_junctions);
         for component in components_to_be_rotated {
             let rotated_component = component.rot_mirror(component.rot_mirror.rotate_r());
-            self.add_component(rotated_component);
+            // one that can't turn without overlapping stays as it was
+            if self.add_component(rotated_component).is_none() {
+                self.add_component(component);
+            }
         }
     }
 
@@ -478,14 +718,275 @@ impl State {
         self.normalize_wires(&dirty_junctions);
         for component in components_to_be_rotated {
             let rotated_component = component.rot_mirror(component.rot_mirror.mirror());
-            self.add_component(rotated_component);
+            if self.add_component(rotated_component).is_none() {
+                self.add_component(component);
+            }
         }
     }
 
-    pub fn add_component(&mut self, component: Component) -> bool {
-        if self.components.locate_in_envelope_intersecting(&component.envelope()).next().is_some() {
+    /// Moves the components in `ids` so that their coordinate on `axis` becomes `target`.
+    /// Nothing is moved and `false` is returned if that would make components overlap.
+    pub fn align_selection(&mut self, ids: &[u32], axis: Axis, target: i32) -> bool {
+        let selected = self
+            .components
+            .iter()
+            .filter(|c| ids.contains(&c.id))
+            .cloned()
+            .collect::<Vec<_>>();
+        let moved = selected
+            .iter()
+            .map(|c| {
+                let mut position = c.position;
+                match axis {
+                    Axis::X => position.x = target,
+                    Axis::Y => position.y = target,
+                }
+                c.position(position)
+            })
+            .collect::<Vec<_>>();
+        for (i, component) in moved.iter().enumerate() {
+            let envelope = component.envelope();
+            let hits_others = self
+                .components
+                .locate_in_envelope_intersecting(&envelope)
+                .any(|c| !ids.contains(&c.id));
+            let hits_selected = moved[..i].iter().any(|c| c.envelope().intersects(&envelope));
+            if hits_others || hits_selected {
+                return false;
+            }
+        }
+        let mut dirty_junctions = vec![];
+        for component in &selected {
+            self.delete_component(component, &mut dirty_junctions);
+        }
+        self.normalize_wires(&dirty_junctions);
+        for component in moved {
+            let added = self.add_component(component);
+            debug_assert!(added.is_some(), "overlaps were ruled out above");
+        }
+        true
+    }
+
+    /// Copies the components in `ids` and the wires lying within their bounds into a snippet
+    /// whose origin is the top-left of those bounds, snapped down to `grid_size`.
+    pub fn extract(&self, ids: &[u32], grid_size: i32) -> Snippet {
+        let selected = self
+            .components
+            .iter()
+            .filter(|c| ids.contains(&c.id))
+            .collect::<Vec<_>>();
+        let bounds = match selected.iter().map(|c| c.envelope()).reduce(|a, b| a.merged(&b)) {
+            Some(bounds) => bounds,
+            None => {
+                return Snippet {
+                    wires: vec![],
+                    components: vec![],
+                }
+            }
+        };
+        let lower = bounds.lower();
+        let origin = Vector2::new(
+            lower[0].div_euclid(grid_size) * grid_size,
+            lower[1].div_euclid(grid_size) * grid_size,
+        );
+        let local = |p: [i32; 2]| [p[0] - origin.x, p[1] - origin.y];
+        let wires = self
+            .wires
+            .locate_in_envelope(&bounds)
+            .map(|wire| Line::new(local(wire.from), local(wire.to)))
+            .collect();
+        let components = selected
+            .into_iter()
+            .map(|c| Component {
+                id: 0,
+                ..c.position(c.position - origin)
+            })
+            .collect();
+        Snippet { wires, components }
+    }
+
+    /// Adds `snippet` with its origin at `at`. Labels already in use are renumbered, keeping
+    /// components that shared a label together. Nothing is added and `None` is returned if a
+    /// component would overlap an existing one; otherwise the new component ids.
+    pub fn paste(&mut self, snippet: Snippet, at: Vector2<i32>) -> Option<Vec<u32>> {
+        let placed = snippet
+            .components
+            .iter()
+            .map(|c| c.position(c.position + at))
+            .collect::<Vec<_>>();
+        for (i, component) in placed.iter().enumerate() {
+            let envelope = component.envelope();
+            let hits_existing = self
+                .components
+                .locate_in_envelope_intersecting(&envelope)
+                .next()
+                .is_some();
+            let hits_pasted = placed[..i].iter().any(|c| c.envelope().intersects(&envelope));
+            if hits_existing || hits_pasted {
+                return None;
+            }
+        }
+
+        let mut taken = self
+            .components
+            .iter()
+            .map(|c| c.label.clone())
+            .collect::<HashSet<_>>();
+        let mut renamed = HashMap::<String, String>::new();
+        let mut ids = vec![];
+        for mut component in placed {
+            if !component.label.is_empty() {
+                let label = match renamed.get(&component.label) {
+                    Some(label) => label.clone(),
+                    None => {
+                        let label = unique_label(&component.label, &taken);
+                        taken.insert(label.clone());
+                        renamed.insert(component.label.clone(), label.clone());
+                        label
+                    }
+                };
+                component.label = label;
+            }
+            component.id = 0;
+            ids.extend(self.add_component(component));
+        }
+        for wire in snippet.wires {
+            let global = |p: [i32; 2]| [p[0] + at.x, p[1] + at.y];
+            self.add_wire_line(Line::new(global(wire.from), global(wire.to)));
+        }
+        Some(ids)
+    }
+
+    /// Deletes the component with `id`. Returns `false` if there is none.
+    pub fn delete_component_by_id(&mut self, id: u32) -> bool {
+        let component = match self.components.iter().find(|c| c.id == id) {
+            Some(component) => component.clone(),
+            None => return false,
+        };
+        let mut dirty_junctions = vec![];
+        self.delete_component(&component, &mut dirty_junctions);
+        self.normalize_wires(&dirty_junctions);
+        true
+    }
+
+    /// Changes the symbol of the component with `id`, keeping its position, orientation, label
+    /// and value. Returns `false` if there is no such component or the new symbol would
+    /// overlap another one.
+    pub fn replace_component_kind(&mut self, id: u32, new_kind: symbol::Kind) -> bool {
+        let component = match self.components.iter().find(|c| c.id == id) {
+            Some(component) => component.clone(),
+            None => return false,
+        };
+        let replaced = Component {
+            id,
+            value: component.value.clone(),
+            ..Component::new(
+                component.position,
+                new_kind,
+                component.rot_mirror,
+                component.label.clone(),
+            )
+        };
+        if self
+            .components
+            .locate_in_envelope_intersecting(&replaced.envelope())
+            .any(|c| c.id != id)
+        {
             return false;
         }
+        let mut dirty_junctions = vec![];
+        self.delete_component(&component, &mut dirty_junctions);
+        self.normalize_wires(&dirty_junctions);
+        self.add_component(replaced).is_some()
+    }
+
+    /// Moves the component with `id` to `position`, unless it would overlap another one.
+    pub fn move_component(&mut self, id: u32, position: Vector2<i32>) -> bool {
+        let component = match self.components.iter().find(|c| c.id == id) {
+            Some(component) => component.clone(),
+            None => return false,
+        };
+        let moved = component.position(position);
+        if self
+            .components
+            .locate_in_envelope_intersecting(&moved.envelope())
+            .any(|c| c.id != id)
+        {
+            return false;
+        }
+        let mut dirty_junctions = vec![];
+        self.delete_component(&component, &mut dirty_junctions);
+        self.normalize_wires(&dirty_junctions);
+        self.add_component(moved).is_some()
+    }
+
+    /// Drops wires that exactly duplicate another one and recounts the junctions from the
+    /// remaining wire ends and pads. `add_wire` never creates duplicates, but hand-edited or
+    /// generated files may contain them. Returns the number of wires dropped.
+    pub fn dedup_wires(&mut self) -> usize {
+        let mut seen = HashSet::new();
+        let wires = self
+            .wires
+            .iter()
+            .map(|wire| {
+                let from = [wire.from[0].min(wire.to[0]), wire.from[1].min(wire.to[1])];
+                let to = [wire.from[0].max(wire.to[0]), wire.from[1].max(wire.to[1])];
+                Line::new(from, to)
+            })
+            .filter(|wire| seen.insert((wire.from, wire.to)))
+            .collect::<Vec<_>>();
+        let dropped = self.wires.size() - wires.len();
+
+        let mut junctions = Junctions::default();
+        for wire in &wires {
+            junctions.incr_by(wire.from, 1);
+            junctions.incr_by(wire.to, 1);
+        }
+        for component in self.components.iter() {
+            for pad in component.pads() {
+                junctions.incr_by(pad.position.into(), 1);
+            }
+        }
+        self.wires = RTree::bulk_load(wires);
+        self.junctions = junctions;
+        self.revision += 1;
+        dropped
+    }
+
+    /// Gives every component a unique id, keeping existing ones where possible.
+    /// Needed after loading since ids from older files may be missing or duplicated.
+    pub fn assign_ids(&mut self) {
+        self.revision += 1;
+        let mut components = self.components.iter().cloned().collect::<Vec<_>>();
+        let mut seen = HashSet::new();
+        for component in &mut components {
+            if component.id == 0 || component.id == u32::MAX || !seen.insert(component.id) {
+                component.id = 0;
+            }
+        }
+        self.next_id = seen.iter().max().map_or(1, |max| max + 1);
+        for component in &mut components {
+            if component.id == 0 {
+                component.id = take_id(&mut self.next_id, |id| seen.contains(&id));
+                seen.insert(component.id);
+            }
+        }
+        self.components = RTree::bulk_load(components);
+    }
+
+    /// Adds `component` unless it overlaps another one, returning the id it was added with.
+    /// It gets a fresh id unless it already has one that isn't in use.
+    pub fn add_component(&mut self, mut component: Component) -> Option<u32> {
+        if self.components.locate_in_envelope_intersecting(&component.envelope()).next().is_some() {
+            return None;
+        }
+        let components = &self.components;
+        let taken = |id| components.iter().any(|c: &Component| c.id == id);
+        if component.id == 0 || component.id == u32::MAX || taken(component.id) {
+            component.id = take_id(&mut self.next_id, taken);
+        } else {
+            self.next_id = self.next_id.max(component.id + 1);
+        }
         for pad in component.pads() {
             let p = pad.position;
             let contacting_wires = self
@@ -508,8 +1009,111 @@ impl State {
             }
             self.junctions.incr_by(p.into(), 1);
         }
+        let id = component.id;
         self.components.insert(component);
-        true
+        self.revision += 1;
+        Some(id)
+    }
+
+    /// Adds `component` like `add_component`, then extends any dangling wire end lying one grid
+    /// step from an unconnected pad, in line with the wire, to reach the pad.
+    pub fn add_component_auto_connect(
+        &mut self,
+        component: Component,
+        grid_size: i32,
+    ) -> Option<u32> {
+        let pads = component.pads().map(|pad| pad.position).collect::<Vec<_>>();
+        let id = self.add_component(component)?;
+        let count_at = |state: &Self, p: [i32; 2]| {
+            state.junctions.rtree.locate_at_point(&p).map_or(0, |j| j.data)
+        };
+        for pad in pads {
+            if count_at(self, pad.into()) != 1 {
+                continue;
+            }
+            let steps = [
+                Vector2::new(grid_size, 0),
+                Vector2::new(-grid_size, 0),
+                Vector2::new(0, grid_size),
+                Vector2::new(0, -grid_size),
+            ];
+            let extension = steps.iter().find_map(|step| {
+                let end: [i32; 2] = (pad + step).into();
+                if count_at(self, end) != 1 {
+                    return None;
+                }
+                let in_line = |wire: &&Line<[i32; 2]>| {
+                    if step.y == 0 {
+                        Horizontal::is_para(**wire)
+                    } else {
+                        Vertical::is_para(**wire)
+                    }
+                };
+                self.wires
+                    .locate_in_envelope_intersecting(&AABB::from_point(end))
+                    .find(in_line)
+                    .map(|_| Line::new(end, pad.into()))
+            });
+            if let Some(extension) = extension {
+                self.add_wire_line(extension);
+            }
+        }
+        Some(id)
+    }
+
+    /// Adds `component` at its own position or, when that overlaps, at the closest free point
+    /// at most `max_steps` grid steps away. Returns the position it was placed at.
+    pub fn add_component_nearest_free(
+        &mut self,
+        component: Component,
+        grid_size: i32,
+        max_steps: i32,
+    ) -> Option<Vector2<i32>> {
+        let origin = component.position;
+        for r in 0..=max_steps {
+            let mut ring = (-r..=r)
+                .flat_map(|dx| (-r..=r).map(move |dy| Vector2::new(dx, dy)))
+                .filter(|d| d.x.abs() == r || d.y.abs() == r)
+                .collect::<Vec<_>>();
+            ring.sort_by_key(|d| d.x * d.x + d.y * d.y);
+            for d in ring {
+                let candidate = component.position(origin + d * grid_size);
+                let position = candidate.position;
+                if self.add_component(candidate).is_some() {
+                    return Some(position);
+                }
+            }
+        }
+        None
+    }
+
+    /// Whether any wire, junction or component intersects `aabb`.
+    pub fn any_in(&self, aabb: &AABB<[i32; 2]>) -> bool {
+        self.wires.locate_in_envelope_intersecting(aabb).next().is_some()
+            || self.junctions.rtree.locate_in_envelope_intersecting(aabb).next().is_some()
+            || self.components.locate_in_envelope_intersecting(aabb).next().is_some()
+    }
+
+    /// Points in `aabb` where a horizontal and a vertical wire cross without connecting.
+    pub fn crossings(&self, aabb: &AABB<[i32; 2]>) -> Vec<[i32; 2]> {
+        let mut crossings = vec![];
+        for &wire_h in self.wires_iter(aabb).filter(|&&wire| Horizontal::is_para(wire)) {
+            let y = Horizontal::perp(wire_h);
+            let (x1, x2) = ord(Horizontal::start(wire_h), Horizontal::end(wire_h));
+            for &wire_v in self
+                .wires
+                .locate_in_envelope_intersecting(&wire_h.envelope())
+                .filter(|&&wire| Vertical::is_para(wire))
+            {
+                let x = Vertical::perp(wire_v);
+                let (y1, y2) = ord(Vertical::start(wire_v), Vertical::end(wire_v));
+                // wires that meet are split there, so a crossing is inside both
+                if x1 < x && x < x2 && y1 < y && y < y2 {
+                    crossings.push([x, y]);
+                }
+            }
+        }
+        crossings
     }
 
     pub fn wires_iter(&self, aabb: &AABB<[i32; 2]>) -> impl Iterator<Item = &Line<[i32; 2]>> {
@@ -526,15 +1130,42 @@ impl State {
             .map(|p| (Vector2::from(*p.geom()), p.data))
     }
 
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn wire_count(&self) -> usize {
+        self.wires.size()
+    }
+
+    pub fn component_count(&self) -> usize {
+        self.components.size()
+    }
+
+    /// All components ordered by label, then position, so lists stay put across edits.
+    pub fn components_sorted(&self) -> Vec<&Component> {
+        let mut components = self.components.iter().collect::<Vec<_>>();
+        components.sort_by(|a, b| {
+            (&a.label, a.position.x, a.position.y).cmp(&(&b.label, b.position.x, b.position.y))
+        });
+        components
+    }
+
+    pub fn components(&self) -> impl Iterator<Item = &Component> {
+        self.components.iter()
+    }
+
     pub fn components_iter(&self, aabb: AABB<[i32; 2]>) -> impl Iterator<Item = &Component> {
         self.components.locate_in_envelope_intersecting(&aabb)
     }
 
     pub fn components_iter_mut(&mut self, aabb: AABB<[i32; 2]>) -> impl Iterator<Item = &mut Component> {
+        self.revision += 1;
         self.components.locate_in_envelope_intersecting_mut(&aabb)
     }
 
-    pub fn build_netlist(&self) -> zuse_core::net::Netlist {
+    /// Assigns a net to every wire endpoint and pad. Net 0 is the power net.
+    pub fn connectivity(&self) -> HashMap<[i32; 2], u32> {
         let mut max_net = 0;
         let mut net_alias = HashMap::<u32, u32>::new();
         let min_net = |net_alias: &HashMap<u32, u32>, mut net: u32| loop {
@@ -545,25 +1176,41 @@ impl State {
         };
         let mut net_map = HashMap::<[i32; 2], u32>::new();
         for component in self.components.iter() {
-            if let symbol::Kind::Power = component.symbol {
-                net_map.insert(component.position.into(), 0);
+            if component.symbol.def().is_source() {
+                for pad in component.pads() {
+                    net_map.insert(pad.position.into(), 0);
+                }
             }
         }
-        for wire in self.wires.iter() {
-            let from_entry = net_map.get(&wire.from);
-            let to_entry = net_map.get(&wire.to);
+        let mut jumpers = vec![];
+        for component in self.components.iter() {
+            if component.symbol.def().is_pass_through() {
+                let mut pads = component.pads().map(|pad| pad.position.into());
+                if let Some(first) = pads.next() {
+                    jumpers.extend(pads.map(|p| (first, p)));
+                }
+            }
+        }
+        let connections = self
+            .wires
+            .iter()
+            .map(|wire| (wire.from, wire.to))
+            .chain(jumpers);
+        for (from, to) in connections {
+            let from_entry = net_map.get(&from);
+            let to_entry = net_map.get(&to);
             match (from_entry, to_entry) {
                 (None, None) => {
                     max_net += 1;
                     let new_net = max_net;
-                    net_map.insert(wire.from, new_net);
-                    net_map.insert(wire.to, new_net);
+                    net_map.insert(from, new_net);
+                    net_map.insert(to, new_net);
                 }
                 (None, Some(&net)) => {
-                    net_map.insert(wire.from, net);
+                    net_map.insert(from, net);
                 }
                 (Some(&net), None) => {
-                    net_map.insert(wire.to, net);
+                    net_map.insert(to, net);
                 }
                 (Some(&net1), Some(&net2)) => {
                     if net1 != net2 {
@@ -577,57 +1224,693 @@ impl State {
         for (p, net) in net_map.into_iter() {
             uni_net_map.insert(p, min_net(&net_alias, net));
         }
-        let mut relays = vec![];
-        let mut switches = vec![];
         for component in self.components.iter() {
-            match component.symbol {
-                symbol::Kind::Power => {}
-                symbol::Kind::Contact => {
-                    let mut pads = component.pads();
-                    let pad_c = pads.next().unwrap();
-                    let pad_a = pads.next().unwrap();
-                    let pad_b = pads.next().unwrap();
-                    let pad_c_p: [i32; 2] = pad_c.position.into();
-                    let pad_a_p: [i32; 2] = pad_a.position.into();
-                    let pad_b_p: [i32; 2] = pad_b.position.into();
-                    let pad_c_net = uni_net_map.get(&pad_c_p).copied().unwrap_or_else(|| {
-                        max_net += 1;
-                        max_net
-                    });
-                    let pad_a_net = uni_net_map.get(&pad_a_p).copied().unwrap_or_else(|| {
-                        max_net += 1;
-                        max_net
-                    });
-                    let pad_b_net = uni_net_map.get(&pad_b_p).copied().unwrap_or_else(|| {
-                        max_net += 1;
-                        max_net
-                    });
-                    switches.push(zuse_core::net::Switch {
-                        state: format!("{}.A", component.label),
-                        l: format!("N{}", pad_c_net),
-                        r: format!("N{}", pad_a_net),
-                    });
-                    switches.push(zuse_core::net::Switch {
-                        state: format!("{}.B", component.label),
-                        l: format!("N{}", pad_c_net),
-                        r: format!("N{}", pad_b_net),
-                    });
+            for pad in component.pads() {
+                uni_net_map.entry(pad.position.into()).or_insert_with(|| {
+                    max_net += 1;
+                    max_net
+                });
+            }
+        }
+        uni_net_map
+    }
+
+    /// Pads of `component` in symbol order, each with whether it is connected to anything.
+    pub fn pad_connections(&self, component: &Component) -> Vec<PadConnection> {
+        component
+            .pads()
+            .map(|pad| {
+                let position: [i32; 2] = pad.position.into();
+                // the pad itself accounts for one
+                let count = self
+                    .junctions
+                    .rtree
+                    .locate_at_point(&position)
+                    .map_or(0, |j| j.data);
+                PadConnection {
+                    name: pad.name.to_string(),
+                    position,
+                    connected: count >= 2,
                 }
-                symbol::Kind::Coil => {
-                    let pad = component.pads().next().unwrap();
-                    let p: [i32; 2] = pad.position.into();
-                    let net = uni_net_map.get(&p).copied().unwrap_or_else(|| {
-                        max_net += 1;
-                        max_net
+            })
+            .collect()
+    }
+
+    /// Checks for unconnected pads and wire ends, switches whose both sides are on the same
+    /// net, and relays driven by more than one coil.
+    pub fn drc(&self) -> Vec<Issue> {
+        self.drc_with(&self.connectivity())
+    }
+
+    fn drc_with(&self, connectivity: &HashMap<[i32; 2], u32>) -> Vec<Issue> {
+        let mut issues = vec![];
+        let mut pad_positions = HashSet::new();
+        let mut relay_owners = HashMap::<String, &Component>::new();
+        for component in self.components.iter() {
+            for pad in self.pad_connections(component) {
+                pad_positions.insert(pad.position);
+                if !pad.connected {
+                    issues.push(Issue {
+                        severity: Severity::Warning,
+                        kind: "floating_pad",
+                        message: format!("{} pad {} is not connected", component.label, pad.name),
+                        position: pad.position,
+                        component: Some(component.id),
                     });
-                    relays.push(zuse_core::net::Relay {
-                        coil: format!("N{}", net),
-                        a: format!("{}.A", &component.label),
-                        b: format!("{}.B", &component.label),
+                }
+            }
+
+            let nets = component
+                .pads()
+                .map(|pad| format!("N{}", connectivity[&<[i32; 2]>::from(pad.position)]))
+                .collect::<Vec<_>>();
+            let mut netlist = zuse_core::net::Netlist {
+                relays: vec![],
+                switches: vec![],
+            };
+            component
+                .symbol
+                .def()
+                .build_netlist(&component.label, &nets, &mut netlist);
+            for switch in &netlist.switches {
+                if switch.l == switch.r {
+                    issues.push(Issue {
+                        severity: Severity::Error,
+                        kind: "shorted_switch",
+                        message: format!("both sides of {} are on the same net", switch.state),
+                        position: component.position.into(),
+                        component: Some(component.id),
                     });
                 }
             }
+            for relay in &netlist.relays {
+                match relay_owners.get(&relay.a) {
+                    Some(owner) if owner.id != component.id => issues.push(Issue {
+                        severity: Severity::Error,
+                        kind: "duplicate_relay",
+                        message: format!("{} is driven by more than one coil", component.label),
+                        position: component.position.into(),
+                        component: Some(component.id),
+                    }),
+                    _ => {
+                        relay_owners.insert(relay.a.clone(), component);
+                    }
+                }
+            }
+        }
+        for (p, count) in self.junctions_iter(&AABB::from_corners([i32::MIN; 2], [i32::MAX; 2])) {
+            let p: [i32; 2] = p.into();
+            if count == 1 && !pad_positions.contains(&p) {
+                issues.push(Issue {
+                    severity: Severity::Warning,
+                    kind: "dangling_wire",
+                    message: "wire end is not connected".to_string(),
+                    position: p,
+                    component: None,
+                });
+            }
+        }
+        issues
+    }
+
+    pub fn netlist_stats(&self) -> NetlistStats {
+        let connectivity = self.connectivity();
+        let netlist = self.build_netlist_with(&connectivity);
+        let issues = self.drc_with(&connectivity);
+        let count = |kind: &str| issues.iter().filter(|issue| issue.kind == kind).count();
+        NetlistStats {
+            nets: connectivity.values().collect::<HashSet<_>>().len(),
+            relays: netlist.relays.len(),
+            switches: netlist.switches.len(),
+            floating_pads: count("floating_pad"),
+            shorted_switches: count("shorted_switch"),
+        }
+    }
+
+    /// All nets with their connection points and the pads on them, ordered by id.
+    pub fn nets(&self) -> Vec<Net> {
+        let connectivity = self.connectivity();
+        let mut nets = HashMap::<u32, Net>::new();
+        for (&p, &id) in &connectivity {
+            nets.entry(id)
+                .or_insert_with(|| Net {
+                    id,
+                    points: vec![],
+                    pads: vec![],
+                })
+                .points
+                .push(p);
+        }
+        for component in self.components.iter() {
+            for pad in component.pads() {
+                let id = connectivity[&<[i32; 2]>::from(pad.position)];
+                nets.get_mut(&id).unwrap().pads.push(NetPad {
+                    component: component.id,
+                    label: component.label.clone(),
+                    pad: pad.name.to_string(),
+                });
+            }
+        }
+        let mut nets = nets.into_values().collect::<Vec<_>>();
+        for net in &mut nets {
+            net.points.sort_unstable();
+            net.pads.sort_by_key(|pad| pad.component);
+        }
+        nets.sort_by_key(|net| net.id);
+        nets
+    }
+
+    pub fn build_netlist(&self) -> zuse_core::net::Netlist {
+        self.build_netlist_with(&self.connectivity())
+    }
+
+    fn build_netlist_with(&self, connectivity: &HashMap<[i32; 2], u32>) -> zuse_core::net::Netlist {
+        let mut netlist = zuse_core::net::Netlist {
+            relays: vec![],
+            switches: vec![],
+        };
+        for component in self.components.iter() {
+            let nets = component
+                .pads()
+                .map(|pad| format!("N{}", connectivity[&<[i32; 2]>::from(pad.position)]))
+                .collect::<Vec<_>>();
+            component
+                .symbol
+                .def()
+                .build_netlist(&component.label, &nets, &mut netlist);
+        }
+        netlist
+    }
+
+    /// The netlist as a Graphviz graph: nets are nodes joined by an edge per switch, and each
+    /// relay coil's net points at the states it drives.
+    pub fn graph_dot(&self) -> String {
+        use std::fmt::Write;
+
+        let quote = |s: &str| {
+            format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+        };
+        let netlist = self.build_netlist();
+        let nets = netlist
+            .switches
+            .iter()
+            .flat_map(|switch| vec![&switch.l, &switch.r])
+            .chain(netlist.relays.iter().map(|relay| &relay.coil))
+            .collect::<std::collections::BTreeSet<_>>();
+        let mut dot = String::from("digraph netlist {\n");
+        for net in nets {
+            writeln!(dot, "  {} [shape=ellipse];", quote(net)).unwrap();
+        }
+        for relay in &netlist.relays {
+            for state in &[&relay.a, &relay.b] {
+                writeln!(dot, "  {} [shape=box];", quote(state)).unwrap();
+                let coil = quote(&relay.coil);
+                writeln!(dot, "  {} -> {} [style=dashed];", coil, quote(state)).unwrap();
+            }
+        }
+        for switch in &netlist.switches {
+            writeln!(
+                dot,
+                "  {} -> {} [dir=none, label={}];",
+                quote(&switch.l),
+                quote(&switch.r),
+                quote(&switch.state)
+            )
+            .unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wires(state: &State) -> Vec<([i32; 2], [i32; 2])> {
+        let mut wires = state.wires.iter().map(|w| (w.from, w.to)).collect::<Vec<_>>();
+        wires.sort_unstable();
+        wires
+    }
+
+    fn junction(state: &State, p: [i32; 2]) -> u8 {
+        state.junctions.rtree.locate_at_point(&p).map_or(0, |j| j.data)
+    }
+
+    #[test]
+    fn drag_wire_stretches_attached_wires() {
+        let mut state = State::default();
+        state.add_wire(WireH { y: 0, x1: 0, x2: 100 });
+        state.add_wire(WireV { x: 0, y1: 0, y2: 100 });
+        state.add_wire(WireV { x: 100, y1: 0, y2: 100 });
+
+        state.drag_wire(Line::new([0, 0], [100, 0]), -50, true);
+
+        assert_eq!(
+            wires(&state),
+            vec![
+                ([0, -50], [0, 100]),
+                ([0, -50], [100, -50]),
+                ([100, -50], [100, 100]),
+            ]
+        );
+        assert_eq!(junction(&state, [0, -50]), 2);
+        assert_eq!(junction(&state, [100, -50]), 2);
+        assert_eq!(junction(&state, [0, 0]), 0);
+        assert_eq!(junction(&state, [100, 0]), 0);
+    }
+
+    #[test]
+    fn dpdt_emits_a_switch_per_throw() {
+        let mut state = State::default();
+        state.add_component(Component::new(
+            Vector2::new(0, 0),
+            symbol::Kind::DPDT,
+            RotMirror::default(),
+            "R1".to_owned(),
+        ));
+
+        let netlist = state.build_netlist();
+        let states = netlist.switches.iter().map(|s| s.state.as_str()).collect::<Vec<_>>();
+        assert_eq!(states, ["R1.A", "R1.B", "R1.A", "R1.B"]);
+        // each pole switches its own common net
+        assert_eq!(netlist.switches[0].l, netlist.switches[1].l);
+        assert_eq!(netlist.switches[2].l, netlist.switches[3].l);
+        assert_ne!(netlist.switches[0].l, netlist.switches[2].l);
+        assert!(netlist.relays.is_empty());
+    }
+
+    fn component(symbol: symbol::Kind, x: i32, y: i32, label: &str) -> Component {
+        Component::new(Vector2::new(x, y), symbol, RotMirror::default(), label.to_owned())
+    }
+
+    #[test]
+    fn align_selection_moves_components_to_a_common_row() {
+        let mut state = State::default();
+        state.add_component(component(symbol::Kind::CONTACT, 0, 0, "R1"));
+        state.add_component(component(symbol::Kind::CONTACT, 200, 50, "R2"));
+        state.add_component(component(symbol::Kind::CONTACT, 400, -100, "R3"));
+        let ids = state.components().map(|c| c.id).collect::<Vec<_>>();
+
+        assert!(state.align_selection(&ids, Axis::Y, 100));
+        let mut positions = state.components().map(|c| c.position).collect::<Vec<_>>();
+        positions.sort_by_key(|p| p.x);
+        assert_eq!(
+            positions,
+            [Vector2::new(0, 100), Vector2::new(200, 100), Vector2::new(400, 100)]
+        );
+
+        // stacking them in one column would overlap, so nothing moves
+        assert!(!state.align_selection(&ids, Axis::X, 0));
+        assert_eq!(state.components().filter(|c| c.position.x == 0).count(), 1);
+    }
+
+    /// Power into the common of contact R1, whose B throw drives coil R1.
+    fn power_contact_coil() -> State {
+        let mut state = State::default();
+        state.add_component(component(symbol::Kind::POWER, 0, 0, "V"));
+        state.add_component(component(symbol::Kind::CONTACT, 0, 200, "R1"));
+        state.add_component(component(symbol::Kind::COIL, 50, 500, "R1"));
+        state.add_wire(WireV { x: 0, y1: 0, y2: 100 });
+        state.add_wire(WireV { x: 50, y1: 300, y2: 400 });
+        state
+    }
+
+    #[test]
+    fn nets_group_points_and_pads() {
+        let state = power_contact_coil();
+        let nets = state.nets();
+        let members = nets
+            .iter()
+            .map(|net| {
+                let pads = net.pads.iter().map(|pad| format!("{}.{}", pad.label, pad.pad));
+                (net.points.clone(), pads.collect::<Vec<_>>())
+            })
+            .collect::<HashSet<_>>();
+        let net = |points: &[[i32; 2]], pads: &[&str]| {
+            (points.to_vec(), pads.iter().map(|pad| pad.to_string()).collect::<Vec<_>>())
+        };
+        assert_eq!(nets.len(), 3);
+        assert_eq!(
+            members,
+            vec![
+                net(&[[0, 0], [0, 100]], &["V.V+", "R1.C"]),
+                net(&[[50, 300], [50, 400]], &["R1.B", "R1.N"]),
+                net(&[[-50, 300]], &["R1.A"]),
+            ]
+            .into_iter()
+            .collect()
+        );
+    }
+
+    #[test]
+    fn any_in_finds_wires_and_components() {
+        let state = power_contact_coil();
+        let region = |x1, y1, x2, y2| AABB::from_corners([x1, y1], [x2, y2]);
+        // the middle of a wire, a component body and an empty area
+        assert!(state.any_in(&region(40, 340, 60, 360)));
+        assert!(state.any_in(&region(60, 550, 70, 560)));
+        assert!(!state.any_in(&region(1000, 1000, 2000, 2000)));
+        assert!(!State::default().any_in(&region(-1000, -1000, 1000, 1000)));
+    }
+
+    #[test]
+    fn only_orientations_parse() {
+        assert_eq!("0 -1 1 0".parse(), Ok(RotMirror::default().rotate_r()));
+        assert!("1 1 0 1".parse::<RotMirror>().is_err());
+        assert!("1 0 0".parse::<RotMirror>().is_err());
+    }
+
+    #[test]
+    fn snippet_round_trip_renumbers_labels() {
+        let mut state = State::default();
+        let contact = state.add_component(component(symbol::Kind::CONTACT, 0, 0, "R1")).unwrap();
+        let coil = Component::new(
+            Vector2::new(400, 0),
+            symbol::Kind::COIL,
+            RotMirror::default().rotate_r(),
+            "R2".to_owned(),
+        );
+        let coil = state.add_component(coil).unwrap();
+        let snippet = state.extract(&[contact, coil], 50);
+
+        assert!(state.paste(state.extract(&[contact], 50), Vector2::new(0, 0)).is_none());
+        let ids = state.paste(snippet, Vector2::new(0, 1000)).unwrap();
+
+        let mut pasted = state
+            .components()
+            .filter(|c| ids.contains(&c.id))
+            .map(|c| (c.label.as_str(), c.rot_mirror))
+            .collect::<Vec<_>>();
+        pasted.sort_by_key(|&(label, _)| label);
+        assert_eq!(ids.len(), 2);
+        assert_eq!(
+            pasted,
+            vec![
+                ("R3", RotMirror::default()),
+                ("R4", RotMirror::default().rotate_r())
+            ]
+        );
+    }
+
+    #[test]
+    fn netlist_builds_natively() {
+        let netlist = power_contact_coil().build_netlist();
+        let states = netlist.switches.iter().map(|s| s.state.as_str()).collect::<Vec<_>>();
+        assert_eq!(states, ["R1.A", "R1.B"]);
+        assert_eq!(netlist.relays.len(), 1);
+        let relay = &netlist.relays[0];
+        assert_eq!((relay.a.as_str(), relay.b.as_str()), ("R1.A", "R1.B"));
+        // the coil hangs off the contact's B side
+        assert_eq!(relay.coil, netlist.switches[1].r);
+        assert_ne!(relay.coil, netlist.switches[0].r);
+    }
+
+    #[test]
+    fn partially_wired_contact_has_a_floating_pad() {
+        let mut state = State::default();
+        state.add_component(component(symbol::Kind::CONTACT, 0, 0, "R1"));
+        state.add_wire(WireV { x: 0, y1: -200, y2: -100 });
+        state.add_wire(WireV { x: 50, y1: 100, y2: 200 });
+        let contact = state.components().next().unwrap();
+
+        let pads = state
+            .pad_connections(contact)
+            .into_iter()
+            .map(|pad| (pad.name, pad.connected))
+            .collect::<Vec<_>>();
+        let pad = |name: &str, connected| (name.to_owned(), connected);
+        assert_eq!(pads, vec![pad("C", true), pad("A", false), pad("B", true)]);
+    }
+
+    #[test]
+    fn wire_len_handles_reversed_and_long_wires() {
+        assert_eq!(WireH { y: 0, x1: 100, x2: 0 }.len(), 100);
+        assert_eq!(WireV { x: 0, y1: 0, y2: 100 }.len(), 100);
+        let longest = WireH { y: 0, x1: i32::MAX, x2: i32::MIN };
+        assert_eq!(longest.len(), u32::MAX);
+        assert!(!longest.is_empty());
+
+        // a reversed wire is stored with its ends in order
+        let mut state = State::default();
+        state.add_wire(WireV { x: 0, y1: 100, y2: -100 });
+        assert_eq!(wires(&state), vec![([0, -100], [0, 100])]);
+        assert_eq!(junction(&state, [0, -100]), 1);
+        assert_eq!(junction(&state, [0, 100]), 1);
+    }
+
+    #[test]
+    fn auto_connect_extends_a_wire_one_step_short() {
+        let mut state = State::default();
+        state.add_wire(WireV { x: 0, y1: -300, y2: -150 });
+        // two steps away, out of reach
+        state.add_wire(WireV { x: 400, y1: -300, y2: -200 });
+        let coil = component(symbol::Kind::COIL, 0, 0, "R1");
+        assert!(state.add_component_auto_connect(coil, 50).is_some());
+        let coil = component(symbol::Kind::COIL, 400, 0, "R2");
+        assert!(state.add_component_auto_connect(coil, 50).is_some());
+
+        assert_eq!(
+            wires(&state),
+            vec![([0, -300], [0, -100]), ([400, -300], [400, -200])]
+        );
+        assert_eq!(junction(&state, [0, -100]), 2);
+        assert_eq!(junction(&state, [0, -150]), 0);
+    }
+
+    #[test]
+    fn graph_dot_links_nets_through_switches_and_coils() {
+        let state = power_contact_coil();
+        let netlist = state.build_netlist();
+        let (common, a, b) = (
+            &netlist.switches[0].l,
+            &netlist.switches[0].r,
+            &netlist.switches[1].r,
+        );
+        let dot = state.graph_dot();
+
+        assert!(dot.starts_with("digraph netlist {\n") && dot.ends_with("}\n"));
+        assert_eq!(dot.matches("[shape=ellipse]").count(), 3);
+        for net in [common, a, b] {
+            assert!(dot.contains(&format!("  \"{}\" [shape=ellipse];\n", net)));
+        }
+        assert!(dot.contains("  \"R1.A\" [shape=box];\n"));
+        assert!(dot.contains(&format!("  \"{}\" -> \"R1.B\" [style=dashed];\n", b)));
+        let switch = |r: &str, label: &str| {
+            format!("  \"{}\" -> \"{}\" [dir=none, label=\"{}\"];\n", common, r, label)
+        };
+        assert!(dot.contains(&switch(a, "R1.A")));
+        assert!(dot.contains(&switch(b, "R1.B")));
+    }
+
+    #[test]
+    fn delete_by_id_removes_only_that_component() {
+        let mut state = State::default();
+        state.add_wire(WireV { x: 0, y1: -200, y2: -100 });
+        state.add_component(component(symbol::Kind::CONTACT, 0, 0, "R1"));
+        // stacked at the same point, as a hand-edited file may have them
+        for (id, label) in [(2, "R2"), (3, "R3")] {
+            let stacked = Component {
+                id,
+                ..component(symbol::Kind::CONTACT, 0, 0, label)
+            };
+            for pad in stacked.pads() {
+                state.junctions.incr_by(pad.position.into(), 1);
+            }
+            state.components.insert(stacked);
+        }
+
+        assert!(state.delete_component_by_id(2));
+        assert!(!state.delete_component_by_id(2));
+        let mut left = state.components().map(|c| (c.id, c.label.as_str())).collect::<Vec<_>>();
+        left.sort_unstable();
+        assert_eq!(left, vec![(1, "R1"), (3, "R3")]);
+        // the wire and the two remaining contacts
+        assert_eq!(junction(&state, [0, -100]), 3);
+        assert_eq!(junction(&state, [50, 100]), 2);
+    }
+
+    #[test]
+    fn drc_flags_a_shorted_contact() {
+        let mut state = State::default();
+        let id = state.add_component(component(symbol::Kind::CONTACT, 0, 0, "R1")).unwrap();
+        // C round to A
+        state.add_wire(WireV { x: 0, y1: -150, y2: -100 });
+        state.add_wire(WireH { y: -150, x1: -100, x2: 0 });
+        state.add_wire(WireV { x: -100, y1: -150, y2: 150 });
+        state.add_wire(WireH { y: 150, x1: -100, x2: -50 });
+        state.add_wire(WireV { x: -50, y1: 100, y2: 150 });
+
+        let issues = state
+            .drc()
+            .into_iter()
+            .map(|issue| (issue.severity, issue.kind, issue.component))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            issues,
+            vec![
+                (Severity::Warning, "floating_pad", Some(id)),
+                (Severity::Error, "shorted_switch", Some(id)),
+            ]
+        );
+    }
+
+    #[test]
+    fn netlist_stats_count_a_small_circuit() {
+        let stats = power_contact_coil().netlist_stats();
+        assert_eq!((stats.nets, stats.relays, stats.switches), (3, 1, 2));
+        // the contact's A side
+        assert_eq!(stats.floating_pads, 1);
+        assert_eq!(stats.shorted_switches, 0);
+    }
+
+    #[test]
+    fn replacing_a_contact_with_a_coil_moves_its_pads() {
+        let mut state = State::default();
+        let id = state.add_component(component(symbol::Kind::CONTACT, 0, 0, "R1")).unwrap();
+        state.add_wire(WireV { x: 0, y1: -200, y2: -100 });
+        state.add_wire(WireV { x: 50, y1: 100, y2: 200 });
+
+        assert!(state.replace_component_kind(id, symbol::Kind::COIL));
+        let coil = state.components().next().unwrap();
+        assert_eq!((coil.id, coil.label.as_str()), (id, "R1"));
+        let pads = state.pad_connections(coil);
+        assert_eq!(pads.len(), 1);
+        assert_eq!((pads[0].name.as_str(), pads[0].connected), ("N", true));
+        // the wire that went to B now ends in the air
+        assert_eq!(junction(&state, [0, -100]), 2);
+        assert_eq!(junction(&state, [50, 100]), 1);
+        assert_eq!(junction(&state, [-50, 100]), 0);
+
+        // the coil reaches further down than a contact, into the one below
+        let contact = state.add_component(component(symbol::Kind::CONTACT, 400, 0, "R2"));
+        state.add_component(component(symbol::Kind::CONTACT, 400, 240, "R3")).unwrap();
+        assert!(!state.replace_component_kind(contact.unwrap(), symbol::Kind::COIL));
+        let kept = state.components().find(|c| c.label == "R2").unwrap();
+        assert_eq!(state.pad_connections(kept).len(), 3);
+    }
+
+    #[test]
+    fn components_sorted_ignores_insertion_order() {
+        let parts = [
+            component(symbol::Kind::COIL, 400, 0, "R1"),
+            component(symbol::Kind::CONTACT, 0, 0, "R2"),
+            component(symbol::Kind::CONTACT, 200, 0, "R1"),
+            component(symbol::Kind::POWER, 0, 400, "V"),
+        ];
+        let list = |order: &[usize]| {
+            let mut state = State::default();
+            for &i in order {
+                state.add_component(parts[i].clone());
+            }
+            let sorted = state.components_sorted();
+            sorted.iter().map(|c| (c.label.clone(), c.position)).collect::<Vec<_>>()
+        };
+        let forward = list(&[0, 1, 2, 3]);
+        assert_eq!(forward, list(&[3, 2, 1, 0]));
+        assert_eq!(forward, list(&[2, 0, 3, 1]));
+        let labels = forward.iter().map(|(label, p)| (label.as_str(), p.x)).collect::<Vec<_>>();
+        assert_eq!(labels, vec![("R1", 200), ("R1", 400), ("R2", 0), ("V", 0)]);
+    }
+
+    #[test]
+    fn dedup_collapses_identical_wires() {
+        let mut state = State::default();
+        state.add_component(component(symbol::Kind::CONTACT, 100, 100, "R1"));
+        state.add_wire(WireH { y: 0, x1: 0, x2: 100 });
+        // as a hand-edited file may have them, counted at both ends
+        for duplicate in [Line::new([0, 0], [100, 0]), Line::new([100, 0], [0, 0])] {
+            state.junctions.incr_by(duplicate.from, 1);
+            state.junctions.incr_by(duplicate.to, 1);
+            state.wires.insert(duplicate);
+        }
+        assert_eq!(junction(&state, [0, 0]), 3);
+
+        assert_eq!(state.dedup_wires(), 2);
+        assert_eq!(wires(&state), vec![([0, 0], [100, 0])]);
+        assert_eq!(junction(&state, [0, 0]), 1);
+        // the wire end and the contact's C pad
+        assert_eq!(junction(&state, [100, 0]), 2);
+        assert_eq!(state.dedup_wires(), 0);
+    }
+
+    fn ids(state: &State) -> Vec<(String, u32)> {
+        let mut ids = state.components().map(|c| (c.label.clone(), c.id)).collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids
+    }
+
+    #[test]
+    fn ids_survive_save_and_load() {
+        let mut state = State::default();
+        for (x, label) in [(0, "R1"), (200, "R2"), (400, "R3")] {
+            state.add_component(component(symbol::Kind::CONTACT, x, 0, label));
+        }
+        let r2 = state.components().find(|c| c.label == "R2").unwrap().id;
+        assert!(state.delete_component_by_id(r2));
+
+        let mut loaded: State =
+            serde_json::from_str(&serde_json::to_string(&state).unwrap()).unwrap();
+        loaded.assign_ids();
+        assert_eq!(ids(&loaded), ids(&state));
+        // a part added after loading doesn't reuse a saved id
+        let r4 = loaded.add_component(component(symbol::Kind::CONTACT, 600, 0, "R4"));
+        assert!(!ids(&state).iter().any(|&(_, id)| id == r4.unwrap()));
+    }
+
+    #[test]
+    fn pasted_and_imported_ids_are_fresh() {
+        let mut state = State::default();
+        let taken = Component {
+            id: 7,
+            ..component(symbol::Kind::CONTACT, 0, 0, "R1")
+        };
+        assert_eq!(state.add_component(taken), Some(7));
+        state.add_component(component(symbol::Kind::COIL, 200, 0, "R1"));
+        let existing = state.components().map(|c| c.id).collect::<Vec<_>>();
+
+        let snippet = state.extract(&existing, 50);
+        let pasted = state.paste(snippet, Vector2::new(0, 1000)).unwrap();
+        // imported with an id that is already in use
+        let clash = Component {
+            id: 7,
+            ..component(symbol::Kind::CONTACT, 400, 0, "R9")
+        };
+        let imported = state.add_component(clash).unwrap();
+
+        let mut all = existing.clone();
+        all.extend(&pasted);
+        all.push(imported);
+        let count = all.len();
+        all.sort_unstable();
+        all.dedup();
+        assert_eq!(all.len(), count);
+    }
+
+    #[test]
+    fn ids_at_the_top_of_the_range_are_reassigned() {
+        let mut state = State::default();
+        let top = Component {
+            id: u32::MAX,
+            ..component(symbol::Kind::CONTACT, 0, 0, "R1")
+        };
+        let top = state.add_component(top).unwrap();
+        assert_ne!(top, u32::MAX);
+
+        // as a file may have them
+        for (x, id) in [(200, u32::MAX), (400, u32::MAX - 1), (600, u32::MAX - 1)] {
+            state.components.insert(Component {
+                id,
+                ..component(symbol::Kind::CONTACT, x, 0, "R")
+            });
         }
-        zuse_core::net::Netlist { relays, switches }
+        state.assign_ids();
+        let mut all = state.components().map(|c| c.id).collect::<Vec<_>>();
+        all.sort_unstable();
+        all.dedup();
+        assert_eq!(all.len(), 4);
+        assert!(!all.contains(&u32::MAX));
+        let added = state.add_component(component(symbol::Kind::CONTACT, 800, 0, "R2"));
+        assert!(!all.contains(&added.unwrap()));
     }
 }
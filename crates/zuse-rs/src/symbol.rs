@@ -1,54 +1,159 @@
+use std::convert::TryFrom;
+use std::sync::RwLock;
+
 use nalgebra::Vector2;
 use rstar::AABB;
 use serde::{Serialize, Deserialize};
+use zuse_core::net::Netlist;
 
 use super::schematic::RotMirror;
 
+pub mod custom;
+
+/// Handle to a symbol definition in the registry.
+///
+/// Serialized as the definition's id rather than its index so saved schematics don't
+/// depend on registration order.
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
-pub enum Kind {
-    Power,
-    Contact,
-    Coil,
+#[serde(into = "String", try_from = "String")]
+pub struct Kind(usize);
+
+// must match the order of the built-in definitions in `REGISTRY`
+impl Kind {
+    pub const POWER: Kind = Kind(0);
+    pub const CONTACT: Kind = Kind(1);
+    pub const COIL: Kind = Kind(2);
+    pub const DPDT: Kind = Kind(3);
 }
 
 impl Kind {
+    pub fn from_id(id: &str) -> Option<Kind> {
+        REGISTRY
+            .read()
+            .unwrap()
+            .iter()
+            .position(|def| def.id() == id)
+            .map(Kind)
+    }
+
+    /// Like `from_id`, for ids in other cases such as the lowercase ones in
+    /// `Cad::active_tool`.
+    pub fn from_id_ignore_case(id: &str) -> Option<Kind> {
+        Self::from_id(id).or_else(|| {
+            REGISTRY
+                .read()
+                .unwrap()
+                .iter()
+                .position(|def| def.id().eq_ignore_ascii_case(id))
+                .map(Kind)
+        })
+    }
+
+    pub fn def(self) -> &'static dyn SymbolDef {
+        REGISTRY.read().unwrap()[self.0]
+    }
+
+    pub fn id(self) -> &'static str {
+        self.def().id()
+    }
+
     pub fn aabb(self) -> AABB<[i32; 2]> {
-        match self {
-            Kind::Power => *power::AABB,
-            Kind::Contact => *contact::AABB,
-            Kind::Coil => *coil::AABB
-        }
+        self.def().aabb()
     }
 
     pub fn pads(self) -> &'static Pads {
-        match self {
-            Kind::Power => &power::PADS,
-            Kind::Contact => &contact::PADS,
-            Kind::Coil => &coil::PADS,
-        }
+        self.def().pads()
     }
 
     pub fn can_rotate(self) -> bool {
-        match self {
-            Kind::Power => false,
-            Kind::Contact => true,
-            Kind::Coil => false,
-        }
+        self.def().can_rotate()
     }
 
     pub fn can_mirror(self) -> bool {
-        match self {
-            Kind::Power => false,
-            Kind::Contact => true,
-            Kind::Coil => false,
-        }
+        self.def().can_mirror()
     }
 }
 
+impl From<Kind> for String {
+    fn from(kind: Kind) -> Self {
+        kind.id().to_string()
+    }
+}
+
+impl TryFrom<String> for Kind {
+    type Error = String;
+
+    fn try_from(id: String) -> Result<Self, Self::Error> {
+        Kind::from_id(&id).ok_or_else(|| format!("unknown symbol: {}", id))
+    }
+}
+
+/// Everything the editor and the netlister need to know about one kind of symbol.
+pub trait SymbolDef: Send + Sync {
+    /// Stable id used in saved schematics.
+    fn id(&self) -> &str;
+
+    fn default_label(&self) -> &str;
+
+    fn aabb(&self) -> AABB<[i32; 2]>;
+
+    fn pads(&self) -> &Pads;
+
+    fn can_rotate(&self) -> bool;
+
+    fn can_mirror(&self) -> bool;
+
+    /// `state` looks up the simulated state `<label>.<name>` of the instance being drawn.
+    fn draw(&self, state: &dyn Fn(&str) -> Option<bool>) -> Vec<Draw>;
+
+    /// Whether all pads are tied to the power net.
+    fn is_source(&self) -> bool {
+        false
+    }
+
+    /// Whether all pads are connected to each other, like a jumper.
+    fn is_pass_through(&self) -> bool {
+        false
+    }
+
+    /// Called when the instance is clicked while simulating. Returns whether any state
+    /// was flipped.
+    fn toggle(&self, _label: &str, _circuit: &mut zuse_core::Circuit) -> bool {
+        false
+    }
+
+    /// Adds the relays and switches of one instance. `nets` are the net names of `pads()`, in order.
+    fn build_netlist(&self, label: &str, nets: &[String], netlist: &mut Netlist);
+}
+
+lazy_static::lazy_static! {
+    static ref REGISTRY: RwLock<Vec<&'static dyn SymbolDef>> = RwLock::new(vec![
+        &power::Power,
+        &contact::Contact,
+        &coil::Coil,
+        &dpdt::Dpdt,
+    ]);
+}
+
+/// Adds a definition to the registry. Fails if the id is already taken.
+pub fn register(def: Box<dyn SymbolDef>) -> Result<Kind, String> {
+    let mut registry = REGISTRY.write().unwrap();
+    if registry.iter().any(|registered| registered.id() == def.id()) {
+        return Err(format!("symbol already registered: {}", def.id()));
+    }
+    registry.push(Box::leak(def));
+    Ok(Kind(registry.len() - 1))
+}
+
 #[derive(Debug, Clone)]
 pub enum Draw {
     Line(Vector2<f32>, Vector2<f32>, f32),
     Circle(Vector2<f32>, f32, f32),
+    /// Filled convex polygon.
+    FilledPolygon(Vec<Vector2<f32>>),
+    /// Center, radius, start and end angle in radians, thickness. Angles grow clockwise on
+    /// screen (y is down), and the arc runs from start to end in that direction.
+    Arc(Vector2<f32>, f32, f32, f32, f32),
 }
 
 impl Draw {
@@ -63,10 +168,51 @@ impl Draw {
             Draw::Circle(p, r, thickness) => {
                 Draw::Circle(rot_mirror.apply_f32(*p) + t, *r, *thickness)
             }
+            Draw::FilledPolygon(points) => Draw::FilledPolygon(
+                points
+                    .iter()
+                    .map(|p| rot_mirror.apply_f32(*p) + t)
+                    .collect(),
+            ),
+            Draw::Arc(p, r, start, end, thickness) => {
+                let x = rot_mirror.apply_f32(Vector2::new(1., 0.));
+                let y = rot_mirror.apply_f32(Vector2::new(0., 1.));
+                let phi = x.y.atan2(x.x);
+                let (start, end) = if x.perp(&y) > 0. {
+                    (phi + start, phi + end)
+                } else {
+                    // mirrored: the direction flips, so swap the ends to keep it clockwise
+                    (phi - end, phi - start)
+                };
+                Draw::Arc(rot_mirror.apply_f32(*p) + t, *r, start, end, *thickness)
+            }
         }
     }
 }
 
+/// Moving contact of a changeover resting on `to_b`: the line from the pivot `from` to the
+/// point where it touches the circle of `radius` around `to_b`. Of the two tangents, the one
+/// facing the other throw `to_a` is used so the blade doesn't cross the contact.
+pub fn moving_contact(
+    from: Vector2<f32>,
+    to_a: Vector2<f32>,
+    to_b: Vector2<f32>,
+    radius: f32,
+) -> (Vector2<f32>, Vector2<f32>) {
+    let d = from - to_b;
+    let len2 = d.norm_squared();
+    let s = (len2 - radius * radius).sqrt();
+    let perp = Vector2::new(d.y, -d.x);
+    let t1 = to_b + (d.scale(radius * radius) + perp.scale(radius * s)).unscale(len2);
+    let t2 = to_b + (d.scale(radius * radius) - perp.scale(radius * s)).unscale(len2);
+    let tip = if (t1 - to_a).norm_squared() <= (t2 - to_a).norm_squared() {
+        t1
+    } else {
+        t2
+    };
+    (from, tip)
+}
+
 #[derive(Debug, Clone)]
 pub struct Pad {
     pub position: Vector2<i32>,
@@ -107,8 +253,9 @@ impl std::ops::Deref for Pads {
 }
 
 pub mod power {
-    use super::{Draw, Pad, Pads};
+    use super::{Draw, Pad, Pads, SymbolDef};
     use nalgebra::Vector2;
+    use zuse_core::net::Netlist;
     lazy_static::lazy_static! {
         pub static ref AABB: rstar::AABB<[i32; 2]> = rstar::AABB::from_corners([-50, -100], [50, 0]);
         pub static ref PADS: Pads = Pads::new(vec![
@@ -123,11 +270,50 @@ pub mod power {
             Draw::Line([0., -100.].into(), [1. / (3.0f32).sqrt() * 50., -50.].into(), 6.),
         ];
     }
+
+    pub struct Power;
+
+    impl SymbolDef for Power {
+        fn id(&self) -> &str {
+            "Power"
+        }
+
+        fn default_label(&self) -> &str {
+            "V+"
+        }
+
+        fn aabb(&self) -> rstar::AABB<[i32; 2]> {
+            *AABB
+        }
+
+        fn pads(&self) -> &Pads {
+            &PADS
+        }
+
+        fn can_rotate(&self) -> bool {
+            false
+        }
+
+        fn can_mirror(&self) -> bool {
+            false
+        }
+
+        fn draw(&self, _state: &dyn Fn(&str) -> Option<bool>) -> Vec<Draw> {
+            DRAW.clone()
+        }
+
+        fn is_source(&self) -> bool {
+            true
+        }
+
+        fn build_netlist(&self, _label: &str, _nets: &[String], _netlist: &mut Netlist) {}
+    }
 }
 
 pub mod contact {
-    use super::{Draw, Pad, Pads};
+    use super::{moving_contact, Draw, Pad, Pads, SymbolDef};
     use nalgebra::Vector2;
+    use zuse_core::net::{Netlist, Switch};
     lazy_static::lazy_static! {
         pub static ref AABB: rstar::AABB<[i32; 2]> = rstar::AABB::from_corners([-50, -100], [50, 100]);
         pub static ref PADS: Pads = Pads::new(vec![
@@ -153,17 +339,69 @@ pub mod contact {
             Draw::Line([50., 50.].into(), [50., 50.].into(), 26.),
         ];
         static ref MOVING_CONTACT_LINE: (Vector2<f32>, Vector2<f32>) = {
-            let a = -50f32; // B contact x - C contact x
-            let b = -100f32; // B contact y - C contact y
-            let r = 16f32; // B contact outer radius + half-thickness of moving contact line
-            let x = r * (a * r + b * (a * a + b * b - r * r).sqrt()) / (a * a + b * b) + 50.;
-            let y = r * (b * r - a * (a * a + b * b - r * r).sqrt()) / (a * a + b * b) + 50.;
-            let s = (Vector2::new(x, y) - Vector2::new(0., -50.)).normalize().scale(10.) + Vector2::new(0., -50.);
-            (s, [x, y].into())
+            let pivot = Vector2::new(0., -50.);
+            // B contact outer radius + half-thickness of moving contact line
+            let (_, tip) = moving_contact(pivot, [-50., 50.].into(), [50., 50.].into(), 16.);
+            let s = (tip - pivot).normalize().scale(10.) + pivot;
+            (s, tip)
         };
     }
 
-    pub fn draw(a: bool, b: bool) -> impl Iterator<Item = Draw> {
+    pub struct Contact;
+
+    impl SymbolDef for Contact {
+        fn id(&self) -> &str {
+            "Contact"
+        }
+
+        fn default_label(&self) -> &str {
+            "R"
+        }
+
+        fn aabb(&self) -> rstar::AABB<[i32; 2]> {
+            *AABB
+        }
+
+        fn pads(&self) -> &Pads {
+            &PADS
+        }
+
+        fn can_rotate(&self) -> bool {
+            true
+        }
+
+        fn can_mirror(&self) -> bool {
+            true
+        }
+
+        fn draw(&self, state: &dyn Fn(&str) -> Option<bool>) -> Vec<Draw> {
+            let a = state("A").unwrap_or(false);
+            let b = state("B").unwrap_or(true);
+            draw(a, b).collect()
+        }
+
+        fn toggle(&self, label: &str, circuit: &mut zuse_core::Circuit) -> bool {
+            let a = !circuit.get_state(&format!("{}.A", label)).unwrap_or(false);
+            circuit.set_state(&format!("{}.A", label), a);
+            circuit.set_state(&format!("{}.B", label), !a);
+            true
+        }
+
+        fn build_netlist(&self, label: &str, nets: &[String], netlist: &mut Netlist) {
+            netlist.switches.push(Switch {
+                state: format!("{}.A", label),
+                l: nets[0].clone(),
+                r: nets[1].clone(),
+            });
+            netlist.switches.push(Switch {
+                state: format!("{}.B", label),
+                l: nets[0].clone(),
+                r: nets[2].clone(),
+            });
+        }
+    }
+
+    pub(super) fn draw(a: bool, b: bool) -> impl Iterator<Item = Draw> {
         STATIC_DRAW
             .iter()
             .cloned()
@@ -187,7 +425,8 @@ pub mod contact {
 }
 
 pub mod coil {
-    use super::{Draw, Pad, Pads};
+    use super::{Draw, Pad, Pads, SymbolDef};
+    use zuse_core::net::{Netlist, Relay};
     lazy_static::lazy_static! {
         pub static ref AABB: rstar::AABB<[i32; 2]> = rstar::AABB::from_corners([-50, -100], [50, 150]);
         pub static ref PADS: Pads = Pads::new(vec![
@@ -206,7 +445,47 @@ pub mod coil {
         ];
     }
 
-    pub fn draw(state: bool) -> impl Iterator<Item = Draw> {
+    pub struct Coil;
+
+    impl SymbolDef for Coil {
+        fn id(&self) -> &str {
+            "Coil"
+        }
+
+        fn default_label(&self) -> &str {
+            "R"
+        }
+
+        fn aabb(&self) -> rstar::AABB<[i32; 2]> {
+            *AABB
+        }
+
+        fn pads(&self) -> &Pads {
+            &PADS
+        }
+
+        fn can_rotate(&self) -> bool {
+            false
+        }
+
+        fn can_mirror(&self) -> bool {
+            false
+        }
+
+        fn draw(&self, state: &dyn Fn(&str) -> Option<bool>) -> Vec<Draw> {
+            draw(state("A").unwrap_or(false)).collect()
+        }
+
+        fn build_netlist(&self, label: &str, nets: &[String], netlist: &mut Netlist) {
+            netlist.relays.push(Relay {
+                coil: nets[0].clone(),
+                a: format!("{}.A", label),
+                b: format!("{}.B", label),
+            });
+        }
+    }
+
+    fn draw(state: bool) -> impl Iterator<Item = Draw> {
         STATIC_DRAW
             .iter()
             .cloned()
@@ -219,3 +498,187 @@ pub mod coil {
             }))
     }
 }
+
+pub mod dpdt {
+    use super::{contact, Draw, Pad, Pads, SymbolDef};
+    use crate::schematic::RotMirror;
+    use nalgebra::Vector2;
+    use zuse_core::net::{Netlist, Switch};
+
+    /// Distance between the two poles.
+    const POLE_PITCH: i32 = 150;
+
+    lazy_static::lazy_static! {
+        pub static ref AABB: rstar::AABB<[i32; 2]> = rstar::AABB::from_corners([-50, -100], [200, 100]);
+        pub static ref PADS: Pads = Pads::new(vec![
+            Pad {
+                name: "C1",
+                position: [0, -100].into(),
+            },
+            Pad {
+                name: "A1",
+                position: [-50, 100].into(),
+            },
+            Pad {
+                name: "B1",
+                position: [50, 100].into(),
+            },
+            Pad {
+                name: "C2",
+                position: [150, -100].into(),
+            },
+            Pad {
+                name: "A2",
+                position: [100, 100].into(),
+            },
+            Pad {
+                name: "B2",
+                position: [200, 100].into(),
+            },
+        ]);
+    }
+
+    pub struct Dpdt;
+
+    impl SymbolDef for Dpdt {
+        fn id(&self) -> &str {
+            "Dpdt"
+        }
+
+        fn default_label(&self) -> &str {
+            "R"
+        }
+
+        fn aabb(&self) -> rstar::AABB<[i32; 2]> {
+            *AABB
+        }
+
+        fn pads(&self) -> &Pads {
+            &PADS
+        }
+
+        fn can_rotate(&self) -> bool {
+            true
+        }
+
+        fn can_mirror(&self) -> bool {
+            true
+        }
+
+        /// Both poles are changeover contacts following the same coil.
+        fn draw(&self, state: &dyn Fn(&str) -> Option<bool>) -> Vec<Draw> {
+            let a = state("A").unwrap_or(false);
+            let b = state("B").unwrap_or(true);
+            let second = Vector2::new(POLE_PITCH, 0);
+            contact::draw(a, b)
+                .chain(contact::draw(a, b).map(|draw| draw.transform(RotMirror::default(), second)))
+                .collect()
+        }
+
+        fn toggle(&self, label: &str, circuit: &mut zuse_core::Circuit) -> bool {
+            let a = !circuit.get_state(&format!("{}.A", label)).unwrap_or(false);
+            circuit.set_state(&format!("{}.A", label), a);
+            circuit.set_state(&format!("{}.B", label), !a);
+            true
+        }
+
+        fn build_netlist(&self, label: &str, nets: &[String], netlist: &mut Netlist) {
+            for pole in nets.chunks(3) {
+                netlist.switches.push(Switch {
+                    state: format!("{}.A", label),
+                    l: pole[0].clone(),
+                    r: pole[1].clone(),
+                });
+                netlist.switches.push(Switch {
+                    state: format!("{}.B", label),
+                    l: pole[0].clone(),
+                    r: pole[2].clone(),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: Vector2<f32>, expected: [f32; 2]) {
+        let expected = Vector2::from(expected);
+        assert!((actual - expected).norm() < 1e-4, "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn moving_contact_pins_changeover_geometry() {
+        // values of the hand-derived formula this helper replaced
+        let pivot = Vector2::new(0., -50.);
+        let (from, tip) = moving_contact(pivot, [-50., 50.].into(), [50., 50.].into(), 16.);
+        assert_eq!(from, pivot);
+        assert_near(tip, [34.812_466, 55.033_77]);
+
+        let Some(Draw::Line(p1, p2, _)) = contact::draw(false, true).last() else {
+            panic!("the moving contact is drawn last");
+        };
+        assert_near(p1, [3.146_104, -40.507_79]);
+        assert_near(p2, [34.812_466, 55.033_77]);
+    }
+
+    #[test]
+    fn filled_polygon_rotates_each_point() {
+        let points = vec![[0., 0.].into(), [10., 0.].into(), [0., 20.].into()];
+        let triangle = Draw::FilledPolygon(points);
+        let rotated = triangle.transform(RotMirror::default().rotate_r(), Vector2::new(100, 200));
+        let Draw::FilledPolygon(points) = rotated else {
+            panic!("a polygon stays a polygon");
+        };
+        assert_eq!(points.len(), 3);
+        assert_near(points[0], [100., 200.]);
+        assert_near(points[1], [100., 210.]);
+        assert_near(points[2], [80., 200.]);
+    }
+
+    #[test]
+    fn arc_angles_follow_rotation_and_mirror() {
+        use std::f32::consts::PI;
+        let arc = Draw::Arc([10., 0.].into(), 5., 0., PI / 4., 6.);
+        let transform = |rot_mirror: RotMirror| arc.transform(rot_mirror, Vector2::zeros());
+
+        let Draw::Arc(center, r, start, end, _) = transform(RotMirror::default().rotate_r()) else {
+            panic!("an arc stays an arc");
+        };
+        assert_near(center, [0., 10.]);
+        assert_eq!(r, 5.);
+        assert!((start - PI / 2.).abs() < 1e-5);
+        assert!((end - (PI / 2. + PI / 4.)).abs() < 1e-5);
+
+        // mirroring runs the arc the other way, so its ends swap
+        let Draw::Arc(center, _, start, end, _) = transform(RotMirror::default().mirror()) else {
+            panic!("an arc stays an arc");
+        };
+        assert_near(center, [-10., 0.]);
+        assert!((start - (PI - PI / 4.)).abs() < 1e-5);
+        assert!((end - PI).abs() < 1e-5);
+    }
+
+    #[test]
+    fn built_in_kinds_keep_their_ids() {
+        assert_eq!(Kind::POWER.id(), "Power");
+        assert_eq!(Kind::CONTACT.id(), "Contact");
+        assert_eq!(Kind::COIL.id(), "Coil");
+        assert_eq!(Kind::DPDT.id(), "Dpdt");
+    }
+
+    #[test]
+    fn built_in_kinds_read_the_enum_format() {
+        // schematics saved while `Kind` was an enum name the variant
+        for (json, kind) in [
+            ("\"Power\"", Kind::POWER),
+            ("\"Contact\"", Kind::CONTACT),
+            ("\"Coil\"", Kind::COIL),
+            ("\"Dpdt\"", Kind::DPDT),
+        ] {
+            assert_eq!(serde_json::from_str::<Kind>(json).unwrap(), kind);
+            assert_eq!(serde_json::to_string(&kind).unwrap(), json);
+        }
+    }
+}
@@ -0,0 +1,437 @@
+//! Symbols described by JSON at runtime.
+//!
+//! ```json
+//! {
+//!   "id": "Jumper",
+//!   "label": "J",
+//!   "aabb": [[-50, -50], [50, 50]],
+//!   "pads": [{ "name": "1", "position": [-50, 0] }, { "name": "2", "position": [50, 0] }],
+//!   "draw": [{ "type": "line", "from": [-50, 0], "to": [50, 0] }],
+//!   "behavior": { "type": "pass_through" }
+//! }
+//! ```
+//!
+//! A draw item with `"when": "<state>"` is only drawn while that switch state is on, and one
+//! with `"unless": "<state>"` only while it is off, so switches can show their position.
+
+use std::collections::HashSet;
+
+use serde::Deserialize;
+use zuse_core::net::{Netlist, Switch};
+
+use super::{Draw, Pad, Pads, SymbolDef};
+
+#[derive(Debug, Deserialize)]
+pub struct Description {
+    id: String,
+    label: String,
+    aabb: [[i32; 2]; 2],
+    pads: Vec<PadDescription>,
+    #[serde(default)]
+    draw: Vec<DrawItem>,
+    #[serde(default)]
+    can_rotate: bool,
+    #[serde(default)]
+    can_mirror: bool,
+    #[serde(default)]
+    behavior: Behavior,
+}
+
+#[derive(Debug, Deserialize)]
+struct PadDescription {
+    name: String,
+    position: [i32; 2],
+}
+
+#[derive(Debug, Deserialize)]
+struct DrawItem {
+    #[serde(flatten)]
+    shape: DrawDescription,
+    #[serde(default)]
+    when: Option<String>,
+    #[serde(default)]
+    unless: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum DrawDescription {
+    Line {
+        from: [f32; 2],
+        to: [f32; 2],
+        #[serde(default = "default_thickness")]
+        thickness: f32,
+    },
+    Circle {
+        center: [f32; 2],
+        radius: f32,
+        #[serde(default = "default_thickness")]
+        thickness: f32,
+    },
+    /// Filled convex polygon.
+    Polygon { points: Vec<[f32; 2]> },
+    /// Angles in radians, clockwise on screen.
+    Arc {
+        center: [f32; 2],
+        radius: f32,
+        start_angle: f32,
+        end_angle: f32,
+        #[serde(default = "default_thickness")]
+        thickness: f32,
+    },
+}
+
+fn default_thickness() -> f32 {
+    6.
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Behavior {
+    /// Only a drawing; contributes nothing to the netlist.
+    #[default]
+    None,
+    /// All pads belong to one net.
+    PassThrough,
+    /// Switches toggled together by clicking the symbol while simulating.
+    Switch { switches: Vec<SwitchDescription> },
+}
+
+#[derive(Debug, Deserialize)]
+struct SwitchDescription {
+    /// Simulator state name, prefixed with the instance label.
+    state: String,
+    l: String,
+    r: String,
+}
+
+struct CustomSwitch {
+    state: String,
+    l: usize,
+    r: usize,
+}
+
+/// Draws an item only while a switch state is `on`.
+struct Condition {
+    state: String,
+    on: bool,
+}
+
+pub struct Custom {
+    id: String,
+    label: String,
+    aabb: rstar::AABB<[i32; 2]>,
+    pads: Pads,
+    draw: Vec<(Option<Condition>, Draw)>,
+    can_rotate: bool,
+    can_mirror: bool,
+    pass_through: bool,
+    switches: Vec<CustomSwitch>,
+}
+
+impl Custom {
+    pub fn from_description(desc: Description) -> Result<Self, String> {
+        if desc.id.is_empty() {
+            return Err("symbol id is empty".to_string());
+        }
+        let aabb = rstar::AABB::from_corners(desc.aabb[0], desc.aabb[1]);
+        let contains = |p: [f32; 2]| {
+            let (lower, upper) = (aabb.lower(), aabb.upper());
+            lower[0] as f32 <= p[0]
+                && p[0] <= upper[0] as f32
+                && lower[1] as f32 <= p[1]
+                && p[1] <= upper[1] as f32
+        };
+
+        let mut names = HashSet::new();
+        for pad in &desc.pads {
+            if pad.name.is_empty() {
+                return Err("pad name is empty".to_string());
+            }
+            if !names.insert(pad.name.as_str()) {
+                return Err(format!("duplicate pad name: {}", pad.name));
+            }
+            if !contains([pad.position[0] as f32, pad.position[1] as f32]) {
+                return Err(format!("pad {} is outside of aabb", pad.name));
+            }
+        }
+        let pad_index = |name: &str| {
+            desc.pads
+                .iter()
+                .position(|pad| pad.name == name)
+                .ok_or_else(|| format!("unknown pad: {}", name))
+        };
+
+        let states: Vec<&str> = match &desc.behavior {
+            Behavior::Switch { switches } => switches.iter().map(|s| s.state.as_str()).collect(),
+            _ => vec![],
+        };
+        let mut draw = vec![];
+        for item in &desc.draw {
+            let condition = match (&item.when, &item.unless) {
+                (None, None) => None,
+                (Some(state), None) => Some(Condition { state: state.clone(), on: true }),
+                (None, Some(state)) => Some(Condition { state: state.clone(), on: false }),
+                (Some(_), Some(_)) => return Err("draw item has both when and unless".to_string()),
+            };
+            if let Some(condition) = &condition {
+                if !states.contains(&condition.state.as_str()) {
+                    return Err(format!("unknown state: {}", condition.state));
+                }
+            }
+            let shape = match &item.shape {
+                &DrawDescription::Line { from, to, thickness } => {
+                    if !contains(from) || !contains(to) {
+                        return Err("line is outside of aabb".to_string());
+                    }
+                    Draw::Line(from.into(), to.into(), thickness)
+                }
+                &DrawDescription::Circle {
+                    center,
+                    radius,
+                    thickness,
+                } => {
+                    let [x, y] = center;
+                    if !contains([x - radius, y - radius]) || !contains([x + radius, y + radius]) {
+                        return Err("circle is outside of aabb".to_string());
+                    }
+                    Draw::Circle(center.into(), radius, thickness)
+                }
+                &DrawDescription::Arc {
+                    center,
+                    radius,
+                    start_angle,
+                    end_angle,
+                    thickness,
+                } => {
+                    // checked against the full circle
+                    let [x, y] = center;
+                    if !contains([x - radius, y - radius]) || !contains([x + radius, y + radius]) {
+                        return Err("arc is outside of aabb".to_string());
+                    }
+                    Draw::Arc(center.into(), radius, start_angle, end_angle, thickness)
+                }
+                DrawDescription::Polygon { points } => {
+                    if !points.iter().all(|&p| contains(p)) {
+                        return Err("polygon is outside of aabb".to_string());
+                    }
+                    Draw::FilledPolygon(points.iter().map(|&p| p.into()).collect())
+                }
+            };
+            draw.push((condition, shape));
+        }
+
+        let (pass_through, switches) = match &desc.behavior {
+            Behavior::None => (false, vec![]),
+            Behavior::PassThrough => (true, vec![]),
+            Behavior::Switch { switches } => {
+                let switches = switches
+                    .iter()
+                    .map(|switch| {
+                        if switch.state.is_empty() {
+                            return Err("switch state is empty".to_string());
+                        }
+                        Ok(CustomSwitch {
+                            state: switch.state.clone(),
+                            l: pad_index(&switch.l)?,
+                            r: pad_index(&switch.r)?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                (false, switches)
+            }
+        };
+
+        // registered symbols live until the page is reloaded, same as the built-in ones
+        let pads = desc
+            .pads
+            .into_iter()
+            .map(|pad| Pad {
+                name: Box::leak(pad.name.into_boxed_str()),
+                position: pad.position.into(),
+            })
+            .collect();
+
+        Ok(Custom {
+            id: desc.id,
+            label: desc.label,
+            aabb,
+            pads: Pads::new(pads),
+            draw,
+            can_rotate: desc.can_rotate,
+            can_mirror: desc.can_mirror,
+            pass_through,
+            switches,
+        })
+    }
+}
+
+impl SymbolDef for Custom {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn default_label(&self) -> &str {
+        &self.label
+    }
+
+    fn aabb(&self) -> rstar::AABB<[i32; 2]> {
+        self.aabb
+    }
+
+    fn pads(&self) -> &Pads {
+        &self.pads
+    }
+
+    fn can_rotate(&self) -> bool {
+        self.can_rotate
+    }
+
+    fn can_mirror(&self) -> bool {
+        self.can_mirror
+    }
+
+    /// States not known yet, e.g. while editing, count as off.
+    fn draw(&self, state: &dyn Fn(&str) -> Option<bool>) -> Vec<Draw> {
+        self.draw
+            .iter()
+            .filter(|(condition, _)| match condition {
+                Some(condition) => state(&condition.state).unwrap_or(false) == condition.on,
+                None => true,
+            })
+            .map(|(_, draw)| draw.clone())
+            .collect()
+    }
+
+    fn is_pass_through(&self) -> bool {
+        self.pass_through
+    }
+
+    fn toggle(&self, label: &str, circuit: &mut zuse_core::Circuit) -> bool {
+        for switch in &self.switches {
+            let state = format!("{}.{}", label, switch.state);
+            let current = circuit.get_state(&state).unwrap_or(false);
+            circuit.set_state(&state, !current);
+        }
+        !self.switches.is_empty()
+    }
+
+    fn build_netlist(&self, label: &str, nets: &[String], netlist: &mut Netlist) {
+        for switch in &self.switches {
+            netlist.switches.push(Switch {
+                state: format!("{}.{}", label, switch.state),
+                l: nets[switch.l].clone(),
+                r: nets[switch.r].clone(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symbol::{register, Kind};
+
+    fn line(x: f32, when: Option<&str>, unless: Option<&str>) -> DrawItem {
+        DrawItem {
+            shape: DrawDescription::Line {
+                from: [x, -50.],
+                to: [x, 50.],
+                thickness: default_thickness(),
+            },
+            when: when.map(str::to_string),
+            unless: unless.map(str::to_string),
+        }
+    }
+
+    fn push_button() -> Custom {
+        let pad = |name: &str, x| PadDescription {
+            name: name.to_string(),
+            position: [x, 0],
+        };
+        Custom::from_description(Description {
+            id: "PushButton".to_string(),
+            label: "PB".to_string(),
+            aabb: [[-50, -50], [50, 50]],
+            pads: vec![pad("1", -50), pad("2", 50)],
+            draw: vec![line(0., None, None), line(-10., Some("A"), None), line(10., None, Some("A"))],
+            can_rotate: false,
+            can_mirror: false,
+            behavior: Behavior::Switch {
+                switches: vec![SwitchDescription {
+                    state: "A".to_string(),
+                    l: "1".to_string(),
+                    r: "2".to_string(),
+                }],
+            },
+        })
+        .unwrap()
+    }
+
+    fn xs(draws: Vec<Draw>) -> Vec<f32> {
+        draws
+            .into_iter()
+            .map(|draw| match draw {
+                Draw::Line(from, _, _) => from.x,
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn draw_follows_switch_state() {
+        let symbol = push_button();
+        assert_eq!(xs(symbol.draw(&|_| None)), vec![0., 10.]);
+        assert_eq!(xs(symbol.draw(&|name| Some(name == "A"))), vec![0., -10.]);
+        assert_eq!(xs(symbol.draw(&|_| Some(false))), vec![0., 10.]);
+    }
+
+    #[test]
+    fn switch_contributes_to_netlist() {
+        let mut netlist = Netlist {
+            relays: vec![],
+            switches: vec![],
+        };
+        let nets = vec!["N1".to_string(), "N2".to_string()];
+        push_button().build_netlist("PB1", &nets, &mut netlist);
+        assert_eq!(netlist.switches.len(), 1);
+        assert_eq!(netlist.switches[0].state, "PB1.A");
+        assert_eq!((netlist.switches[0].l.as_str(), netlist.switches[0].r.as_str()), ("N1", "N2"));
+    }
+
+    #[test]
+    fn condition_on_unknown_state_is_rejected() {
+        let lamp = |draw| Description {
+            id: "Lamp".to_string(),
+            label: "L".to_string(),
+            aabb: [[-50, -50], [50, 50]],
+            pads: vec![],
+            draw: vec![draw],
+            can_rotate: false,
+            can_mirror: false,
+            behavior: Behavior::None,
+        };
+        assert!(Custom::from_description(lamp(line(0., Some("A"), None))).is_err());
+        assert!(Custom::from_description(lamp(line(0., None, None))).is_ok());
+    }
+
+    #[test]
+    fn module_doc_example_registers() {
+        let json: String = include_str!("custom.rs")
+            .lines()
+            .skip_while(|line| *line != "//! ```json")
+            .skip(1)
+            .take_while(|line| *line != "//! ```")
+            .map(|line| line.trim_start_matches("//!"))
+            .collect();
+        let desc: Description = serde_json::from_str(&json).unwrap();
+        let kind = register(Box::new(Custom::from_description(desc).unwrap())).unwrap();
+        assert_eq!(kind.id(), "Jumper");
+        assert_eq!(Kind::from_id("Jumper"), Some(kind));
+
+        // ids can't be redefined
+        let desc: Description = serde_json::from_str(&json).unwrap();
+        let again = register(Box::new(Custom::from_description(desc).unwrap()));
+        assert!(again.is_err());
+    }
+}
@@ -113,6 +113,10 @@ export const App: React.FC = () => {
                 <dd>
                   <strong>S</strong>witch
                 </dd>
+                <dt>Key T</dt>
+                <dd>
+                  Double-pole swi<strong>t</strong>ch
+                </dd>
                 <dt>Key P</dt>
                 <dd>
                   <strong>P</strong>ower Source
@@ -127,8 +131,20 @@ export const App: React.FC = () => {
                 <dd>
                   <strong>D</strong>elete wires or components
                 </dd>
+                <dt>Key M</dt>
+                <dd>
+                  <strong>M</strong>ove component or wire segment
+                </dd>
+                <dt>Space</dt>
+                <dd>Start or stop simulation</dd>
+                <dt>Arrow keys</dt>
+                <dd>Pan the view (when not pointing at a component)</dd>
+                <dt>Key V</dt>
+                <dd>
+                  Edit component <strong>v</strong>alue
+                </dd>
                 <dt>Double-click component</dt>
-                <dd>Change ID</dd>
+                <dd>Change ID (value while simulating)</dd>
               </dl>
             </div>
           </Dialog>
@@ -124,6 +124,13 @@ export const ZsCad = forwardRef<Handler, ZsCadProps>((_, ref) => {
       }
       io.pushClick(e.button);
     };
+    const onContextMenu = function (this: HTMLCanvasElement, e: MouseEvent) {
+      e.preventDefault();
+      if (!io) {
+        return;
+      }
+      io.pushClick(e.button);
+    };
     const onDoubleClick = function (this: HTMLCanvasElement, e: MouseEvent) {
       if (!io) {
         return;
@@ -137,13 +144,29 @@ export const ZsCad = forwardRef<Handler, ZsCadProps>((_, ref) => {
       }
       io.pushKeydown(e.key);
     };
+    const onKeyUp = function (this: HTMLCanvasElement, e: KeyboardEvent) {
+      e.preventDefault();
+      if (!io) {
+        return;
+      }
+      io.pushKeyup(e.key);
+    };
+    const onBlur = function (this: HTMLCanvasElement, _e: FocusEvent) {
+      if (!io) {
+        return;
+      }
+      io.pushBlur();
+    };
     currentCanvas.addEventListener("wheel", onWheel);
     currentCanvas.addEventListener("mousemove", onMouseMove);
     currentCanvas.addEventListener("mousedown", onMouseDown);
     currentCanvas.addEventListener("mouseup", onMouseUp);
     currentCanvas.addEventListener("click", onClick);
+    currentCanvas.addEventListener("contextmenu", onContextMenu);
     currentCanvas.addEventListener("dblclick", onDoubleClick);
     currentCanvas.addEventListener("keydown", onKeyDown);
+    currentCanvas.addEventListener("keyup", onKeyUp);
+    currentCanvas.addEventListener("blur", onBlur);
     return () => {
       isUnmounted = true;
       currentCanvas.removeEventListener("wheel", onWheel);
@@ -151,7 +174,10 @@ export const ZsCad = forwardRef<Handler, ZsCadProps>((_, ref) => {
       currentCanvas.removeEventListener("mousedown", onMouseDown);
       currentCanvas.removeEventListener("mouseup", onMouseUp);
       currentCanvas.removeEventListener("click", onClick);
+      currentCanvas.removeEventListener("contextmenu", onContextMenu);
       currentCanvas.removeEventListener("keydown", onKeyDown);
+      currentCanvas.removeEventListener("keyup", onKeyUp);
+      currentCanvas.removeEventListener("blur", onBlur);
     };
   }, []);
   return (